@@ -0,0 +1,89 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use solfhe_analyzer::{extract_keywords_from_url, Config, HistoryAnalyzer};
+
+const CORPUS_SIZE: usize = 10_000;
+
+// Hosts and path words the generated corpus is drawn from, mixing crypto sites with the generic
+// ones that make up most of a real history
+const HOSTS: [&str; 16] = [
+    "www.google.com",
+    "solana.com",
+    "docs.solana.com",
+    "explorer.solana.com",
+    "ethereum.org",
+    "github.com",
+    "www.coindesk.com",
+    "polkadot.network",
+    "app.aave.com",
+    "news.ycombinator.com",
+    "stackoverflow.com",
+    "medium.com",
+    "polygonscan.com",
+    "www.youtube.com",
+    "en.m.wikipedia.org",
+    "www.bbc.co.uk",
+];
+const WORDS: [&str; 24] = [
+    "docs", "solana", "staking", "validator", "swap", "bridge", "nft", "defi", "wallet", "governance", "tokens", "ethereum",
+    "polkadot", "zk-rollups", "markets", "news", "blog", "developers", "guides", "rust", "anchor", "airdrop", "Yield", "2024",
+];
+
+// CORPUS_SIZE URLs from a fixed seed: the same corpus on every run and machine, without checking
+// in anyone's history. Paths are 0-3 words deep, a quarter carry tracking parameters and a quarter
+// an opaque ID, and some repeat with different parameters like real revisits do.
+fn corpus() -> Vec<String> {
+    let mut state: u64 = 0x5eed;
+    let mut next = |n: usize| {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        (state >> 33) as usize % n
+    };
+    (0..CORPUS_SIZE)
+        .map(|_| {
+            let host = HOSTS[next(HOSTS.len())];
+            let path: Vec<&str> = (0..next(4)).map(|_| WORDS[next(WORDS.len())]).collect();
+            let query = match next(4) {
+                0 => format!("?utm_source=twitter&utm_medium=social&q={}+{}", WORDS[next(WORDS.len())], WORDS[next(WORDS.len())]),
+                1 => format!("?id={:x}", next(1 << 30)),
+                _ => String::new(),
+            };
+            format!("https://{}/{}{}", host, path.join("/"), query)
+        })
+        .collect()
+}
+
+fn bench_extract_keywords(c: &mut Criterion) {
+    let config = Config::defaults();
+    let corpus = corpus();
+    let mut group = c.benchmark_group("extract_keywords_from_url");
+    group.throughput(Throughput::Elements(corpus.len() as u64));
+    group.bench_function("10k-urls", |b| {
+        b.iter(|| {
+            for url in &corpus {
+                black_box(extract_keywords_from_url(black_box(url), &config));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_analyze_link(c: &mut Criterion) {
+    let corpus = corpus();
+    let mut group = c.benchmark_group("analyze_link");
+    group.throughput(Throughput::Elements(corpus.len() as u64));
+    // A fresh analyzer per iteration so the seen set does not turn every link into a duplicate
+    group.bench_function("10k-urls", |b| {
+        b.iter_batched(
+            || HistoryAnalyzer::new(Config::defaults()).unwrap(),
+            |mut analyzer| {
+                for url in &corpus {
+                    black_box(analyzer.analyze_link(black_box(url)));
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_extract_keywords, bench_analyze_link);
+criterion_main!(benches);
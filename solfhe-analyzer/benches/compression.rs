@@ -0,0 +1,61 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde_json::{json, Value};
+use solfhe_analyzer::{zk_compress, zk_decompress, CompressionBackend, Encoding};
+
+const BACKENDS: [CompressionBackend; 3] = [CompressionBackend::Digest, CompressionBackend::Deflate, CompressionBackend::Zstd];
+
+// An emitted envelope shaped like a real batch: `words` distinct keywords with counts, the
+// top ten of them ranked, and the usual metadata
+fn envelope(words: usize) -> String {
+    let counts: Vec<(String, u32)> = (0..words).map(|i| (format!("keyword{}", i), (words - i) as u32)).collect();
+    let top_words: Vec<Value> = counts.iter().take(10).map(|(word, count)| json!({ "word": word, "count": count, "category": "word" })).collect();
+    let word_counts: serde_json::Map<String, Value> = counts.iter().map(|(word, count)| (word.clone(), json!(count))).collect();
+    json!({
+        "schema_version": 9,
+        "generated_at": "2024-05-01T12:00:00Z",
+        "analyzer_version": "0.1.0",
+        "batch_size": 10,
+        "encoding": "base64",
+        "source": { "browser": "chrome", "profile": "Default" },
+        "warnings": [],
+        "errors": [],
+        "result": {
+            "most_common_word": "keyword0",
+            "count": words,
+            "timestamp": 1714564800,
+            "fingerprint": "3f1d0c9a7b52e684",
+            "links_analyzed": 10,
+            "top_words": top_words,
+            "word_counts": word_counts,
+            "network_domains": { "solana": 3, "ethereum": 1 },
+            "category_counts": { "network": 4, "word": words },
+        }
+    })
+    .to_string()
+}
+
+// Besides timing, prints each backend's compressed size so the ratios sit next to the numbers
+fn bench_backends(c: &mut Criterion) {
+    for words in [10, 100, 1000] {
+        let payload = envelope(words);
+        let mut group = c.benchmark_group(format!("compression/{}-words", words));
+        group.throughput(Throughput::Bytes(payload.len() as u64));
+        for backend in BACKENDS {
+            let compressed = zk_compress(&payload, backend, Encoding::Base64).unwrap();
+            println!("{} words, {}: {} -> {} bytes", words, backend, payload.len(), compressed.payload.len());
+
+            group.bench_with_input(BenchmarkId::new("compress", backend), &payload, |b, payload| {
+                b.iter(|| zk_compress(black_box(payload), backend, Encoding::Base64).unwrap())
+            });
+            if backend.is_reversible() {
+                group.bench_with_input(BenchmarkId::new("decompress", backend), &compressed.payload, |b, compressed| {
+                    b.iter(|| zk_decompress(black_box(compressed), Encoding::Base64).unwrap())
+                });
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_backends);
+criterion_main!(benches);
@@ -17,7 +17,9 @@ blink_links = {
 }
 
 def check_for_blink(data):
-    most_common_word = data.get("most_common_word", "").lower()
+    # Results are wrapped in an envelope with run metadata; older files are the bare result
+    result = data.get("result", data)
+    most_common_word = (result.get("most_common_word") or "").lower()
     if most_common_word in blink_links:
         blink_link = blink_links[most_common_word]
         print(f"{most_common_word} aktif: {blink_link}")
@@ -0,0 +1,793 @@
+use std::collections::{HashMap, HashSet};
+
+use once_cell::sync::Lazy;
+use percent_encoding::percent_decode_str;
+use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, trace};
+use url::{Host, Url};
+
+// The part of the analysis that only looks at URL strings: parsing, domain filtering,
+// tokenization and keyword counting. Nothing here reads files, the browser history or the
+// network, so it builds for wasm32 without the `native` feature (see wasm.rs). HistoryAnalyzer
+// runs the same code, which keeps the extension and the CLI counting alike.
+
+pub(crate) const BLOCKCHAIN_NETWORKS: [&str; 20] = [
+    "bitcoin", "ethereum", "scroll", "polkadot", "solana", "zk-lokomotive", "cosmos",
+    "algorand", "mina", "chainlink", "superteam", "aave", "compound", "maker",
+    "polygon", "binance", "tron", "wormhole", "stellar", "filecoin"
+];
+
+const IGNORED_WORDS: [&str; 18] = [
+    "http", "https", "www", "com", "org", "net", "search", "google", "?", "q", "=", "xyz", "&", "%", "#", "oq", "://", ":UTF-8"
+];
+
+// Built once instead of on every extract_keywords_from_url call
+static IGNORED_WORD_SET: Lazy<HashSet<&'static str>> = Lazy::new(|| IGNORED_WORDS.iter().copied().collect());
+
+// Upper bound on compiled regex size so a pathological pattern can't blow up memory at startup
+const PATTERN_SIZE_LIMIT: usize = 1 << 20;
+
+// A user-defined token pattern (`[extractors]` in the config file), applied to the whole
+// percent-decoded URL. Every match is counted verbatim, so case-sensitive tokens like ticker
+// symbols or base58 addresses survive.
+#[derive(Debug, Clone)]
+pub(crate) struct Extractor {
+    pub(crate) name: String,
+    pub(crate) regex: Regex,
+}
+
+impl Extractor {
+    pub(crate) fn compile(name: &str, pattern: &str) -> Result<Extractor, Box<dyn std::error::Error>> {
+        let regex = RegexBuilder::new(pattern)
+            .size_limit(PATTERN_SIZE_LIMIT)
+            .build()
+            .map_err(|e| format!("Invalid extractor `{}` pattern `{}`: {}", name, pattern, e))?;
+        Ok(Extractor { name: name.to_string(), regex })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct KeywordPattern {
+    label: String,
+    pattern: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeywordsFile {
+    #[serde(default)]
+    networks: Vec<String>,
+    #[serde(default)]
+    patterns: Vec<KeywordPattern>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Keywords {
+    pub(crate) networks: Vec<String>,
+    pattern_set: RegexSet,
+    pattern_labels: Vec<String>,
+}
+
+impl Default for Keywords {
+    fn default() -> Self {
+        Keywords {
+            networks: BLOCKCHAIN_NETWORKS.iter().map(|&s| s.to_string()).collect(),
+            pattern_set: RegexSet::empty(),
+            pattern_labels: Vec::new(),
+        }
+    }
+}
+
+impl Keywords {
+    // `contents` is a keywords JSON file, see the README
+    pub(crate) fn parse(contents: &str) -> Result<Keywords, Box<dyn std::error::Error>> {
+        let file: KeywordsFile = serde_json::from_str(contents)?;
+
+        let mut sources = Vec::new();
+        let mut labels = Vec::new();
+        for entry in file.patterns {
+            // Compile individually first so an error points at the offending pattern
+            RegexBuilder::new(&entry.pattern)
+                .size_limit(PATTERN_SIZE_LIMIT)
+                .build()
+                .map_err(|e| format!("Invalid keyword pattern `{}` for label `{}`: {}", entry.pattern, entry.label, e))?;
+            sources.push(entry.pattern);
+            labels.push(entry.label);
+        }
+
+        let pattern_set = RegexSetBuilder::new(&sources)
+            .size_limit(PATTERN_SIZE_LIMIT)
+            .build()
+            .map_err(|e| format!("Keyword patterns exceed the complexity limit: {}", e))?;
+
+        let networks = if file.networks.is_empty() {
+            Keywords::default().networks
+        } else {
+            file.networks
+        };
+
+        Ok(Keywords { networks, pattern_set, pattern_labels: labels })
+    }
+
+    // Network names are kept as the keywords file spells them until Config knows whether matching
+    // is case-sensitive; for the default case-insensitive matching they are lowercased like tokens
+    pub(crate) fn lowercase_networks(&mut self) {
+        for network in &mut self.networks {
+            *network = network.to_lowercase();
+        }
+    }
+
+    pub(crate) fn category(&self, word: &str) -> KeywordCategory {
+        if self.networks.iter().any(|network| network == word) {
+            KeywordCategory::Network
+        } else if self.pattern_labels.iter().any(|label| label == word) {
+            KeywordCategory::Label
+        } else {
+            KeywordCategory::Word
+        }
+    }
+
+    // Each label is returned at most once, even when several of its patterns match the token
+    pub(crate) fn matching_labels(&self, token: &str) -> HashSet<&str> {
+        self.pattern_set
+            .matches(token)
+            .into_iter()
+            .map(|index| self.pattern_labels[index].as_str())
+            .collect()
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeywordCategory {
+    Network,
+    Label,
+    Word,
+}
+
+impl KeywordCategory {
+    // Same spelling as the serialized form
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeywordCategory::Network => "network",
+            KeywordCategory::Label => "label",
+            KeywordCategory::Word => "word",
+        }
+    }
+}
+
+// `category` is only filled in for top words; trend entries leave it out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WordCount {
+    pub word: String,
+    pub count: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<KeywordCategory>,
+}
+
+// Host list, one entry per line. `*.example.com` matches example.com and every
+// subdomain, `docs.*` matches any host starting with `docs.`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DomainList {
+    exact: HashSet<String>,
+    suffixes: Vec<String>,
+    prefixes: Vec<String>,
+}
+
+impl DomainList {
+    pub(crate) fn parse(contents: &str) -> DomainList {
+        let mut list = DomainList::default();
+        for line in contents.lines() {
+            let entry = line.trim().to_lowercase();
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            if let Some(domain) = entry.strip_prefix("*.") {
+                list.suffixes.push(domain.to_string());
+            } else if let Some(label) = entry.strip_suffix(".*") {
+                list.prefixes.push(format!("{}.", label));
+            } else {
+                list.exact.insert(entry);
+            }
+        }
+        list
+    }
+
+    pub(crate) fn extend(&mut self, other: DomainList) {
+        self.exact.extend(other.exact);
+        self.suffixes.extend(other.suffixes);
+        self.prefixes.extend(other.prefixes);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.suffixes.is_empty() && self.prefixes.is_empty()
+    }
+
+    pub(crate) fn matches(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.exact.contains(&host)
+            || self.suffixes.iter().any(|domain| {
+                host == *domain || host.ends_with(&format!(".{}", domain))
+            })
+            || self.prefixes.iter().any(|prefix| host.starts_with(prefix.as_str()))
+    }
+}
+
+pub(crate) const DEFAULT_DENYLIST: &str = include_str!("../default-denylist.txt");
+
+// Only allowed schemes are tokenized, so anything else (mailto:, ftp:, ...) yields no keywords
+// unless the user opts in; `*` allows every scheme
+pub(crate) const DEFAULT_ALLOWED_SCHEMES: [&str; 2] = ["http", "https"];
+
+// Inline content, scripts and page sources are never browsing signal, even with `*` allowed
+pub(crate) const DEFAULT_DENIED_SCHEMES: [&str; 3] = ["data", "javascript", "view-source"];
+
+// Browser-internal and local file entries carry no browsing signal
+const SKIPPED_SCHEMES: [&str; 3] = ["chrome", "about", "file"];
+
+// Query parameters dropped before deduplication; a trailing `*` matches by prefix
+pub(crate) const DEFAULT_TRACKING_PARAMS: [&str; 4] = ["utm_*", "gclid", "fbclid", "ref"];
+
+// Leading host labels dropped before the domain is split into keywords; `??` stands for any
+// two-letter label (locale subdomains such as `en.` or `de.`)
+pub(crate) const DEFAULT_STRIP_SUBDOMAINS: [&str; 5] = ["www", "m", "mobile", "amp", "??"];
+
+// The settings the tokenizer and the counting depend on. Config fills them from the config file
+// and the command line; the defaults match an analyzer run without either.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    pub(crate) keywords: Keywords,
+    pub(crate) extractors: Vec<Extractor>,
+    pub(crate) excluded_domains: DomainList,
+    pub(crate) allowed_domains: DomainList,
+    pub(crate) extra_ignored_words: HashSet<String>,
+    pub(crate) tracking_params: Vec<String>,
+    pub(crate) strip_subdomains: Vec<String>,
+    pub(crate) allowed_schemes: Vec<String>,
+    pub(crate) denied_schemes: Vec<String>,
+    pub(crate) include_local: bool,
+    // Keep the case of path words (`ETH` and `eth` are different keywords) and match networks
+    // exactly. Hosts are always lowercase.
+    pub(crate) case_sensitive: bool,
+    // Keep tokens that are all digits, like ids and years, see clean_token
+    pub(crate) keep_numeric: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            keywords: Keywords::default(),
+            extractors: Vec::new(),
+            excluded_domains: DomainList::parse(DEFAULT_DENYLIST),
+            allowed_domains: DomainList::default(),
+            extra_ignored_words: HashSet::new(),
+            tracking_params: DEFAULT_TRACKING_PARAMS.iter().map(|&p| p.to_string()).collect(),
+            strip_subdomains: DEFAULT_STRIP_SUBDOMAINS.iter().map(|&label| label.to_string()).collect(),
+            allowed_schemes: DEFAULT_ALLOWED_SCHEMES.iter().map(|&scheme| scheme.to_string()).collect(),
+            denied_schemes: DEFAULT_DENIED_SCHEMES.iter().map(|&scheme| scheme.to_string()).collect(),
+            include_local: false,
+            case_sensitive: false,
+            keep_numeric: false,
+        }
+    }
+}
+
+impl Rules {
+    // The denylist always wins over the allowlist
+    fn scheme_allowed(&self, scheme: &str) -> bool {
+        !self.denied_schemes.iter().any(|denied| denied == scheme)
+            && self.allowed_schemes.iter().any(|allowed| allowed == "*" || allowed == scheme)
+    }
+}
+
+fn is_tracking_param(name: &str, tracking_params: &[String]) -> bool {
+    let name = name.to_lowercase();
+    tracking_params.iter().any(|param| match param.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == *param,
+    })
+}
+
+// Canonical key used for deduplication: http folded into https, a leading `www.` dropped,
+// fragment and tracking parameters removed, the remaining parameters sorted by name, duplicate
+// slashes collapsed and the root path's trailing slash dropped. Scheme and host are lowercased
+// and default ports dropped by the URL parser itself.
+pub fn normalize_url(url: &str, tracking_params: &[String]) -> String {
+    let Ok(mut parsed) = Url::parse(url.trim()) else {
+        return url.trim().to_string();
+    };
+
+    if parsed.scheme() == "http" {
+        // Both schemes are "special" in the URL spec, so this switch cannot fail
+        let _ = parsed.set_scheme("https");
+    }
+    if let Some(host) = parsed.host_str().and_then(|host| host.strip_prefix("www.")).map(str::to_string) {
+        let _ = parsed.set_host(Some(&host));
+    }
+    parsed.set_fragment(None);
+
+    let mut kept: Vec<(String, String)> = parsed.query_pairs()
+        .filter(|(name, _)| !is_tracking_param(name, tracking_params))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    // Stable, so repeated parameters keep their relative order
+    kept.sort_by(|(a, _), (b, _)| a.cmp(b));
+    if kept.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(kept);
+    }
+
+    let mut path = parsed.path().to_string();
+    while path.contains("//") {
+        path = path.replace("//", "/");
+    }
+    parsed.set_path(&path);
+
+    let normalized = parsed.to_string();
+    if parsed.path() == "/" && parsed.query().is_none() {
+        normalized.trim_end_matches('/').to_string()
+    } else {
+        normalized
+    }
+}
+
+// localhost, loopback and private (RFC1918) hosts are local dev traffic, not browsing signal
+fn is_local_host(parsed_url: &Url) -> bool {
+    match parsed_url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.to_lowercase();
+            domain == "localhost" || domain.ends_with(".localhost")
+        }
+        Some(Host::Ipv4(ip)) => ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified(),
+        Some(Host::Ipv6(ip)) => {
+            ip.is_loopback() || ip.is_unspecified() || (ip.segments()[0] & 0xfe00) == 0xfc00 || (ip.segments()[0] & 0xffc0) == 0xfe80
+        }
+        None => false,
+    }
+}
+
+// History entries are not always absolute URLs: bare hosts like `example.com` are retried with an
+// https:// prefix, browser-internal schemes are skipped and anything else unparseable is dropped.
+// The URL itself is left out of the log, which may not show raw URLs (see --anonymize).
+pub(crate) fn parse_history_url(url: &str) -> Option<Url> {
+    let url = url.trim();
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(url::ParseError::RelativeUrlWithoutBase) => match Url::parse(&format!("https://{}", url)) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                debug!("could not parse a history URL: {}", e);
+                return None;
+            }
+        },
+        Err(e) => {
+            debug!("could not parse a history URL: {}", e);
+            return None;
+        }
+    };
+
+    if SKIPPED_SCHEMES.contains(&parsed.scheme()) {
+        return None;
+    }
+    Some(parsed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Exclusion {
+    Denylist,
+    Allowlist,
+}
+
+// Drops leading labels matching the strip-list until only the registrable domain is left, so
+// `en.m.wikipedia.org` tokenizes like `wikipedia.org` while `go.dev` keeps its `go`
+fn strip_subdomains<'a>(domain: &'a str, strip: &[String]) -> &'a str {
+    let mut domain = domain;
+    while domain != registrable_domain(domain) {
+        let Some((label, rest)) = domain.split_once('.') else { break };
+        let stripped = strip.iter().any(|entry| {
+            entry == label || (entry == "??" && label.len() == 2 && label.bytes().all(|b| b.is_ascii_alphabetic()))
+        });
+        if !stripped {
+            break;
+        }
+        domain = rest;
+    }
+    domain
+}
+
+// The host's eTLD+1 by the public suffix list: `example.co.uk` for `www.example.co.uk`. IP
+// addresses and single-label hosts are their own registrable domain.
+pub(crate) fn registrable_domain(host: &str) -> &str {
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return host;
+    }
+    psl::domain_str(host).unwrap_or(host)
+}
+
+// The registrable domain without its public suffix, so `example.com` and `example.co.uk` are
+// both `example`
+pub(crate) fn organization(host: &str) -> &str {
+    let domain = registrable_domain(host);
+    match psl::suffix_str(domain) {
+        Some(suffix) if suffix.len() < domain.len() => &domain[..domain.len() - suffix.len() - 1],
+        _ => domain,
+    }
+}
+
+// The denylist always wins; a non-empty allowlist then restricts analysis to matching domains
+pub(crate) fn exclusion(url: &str, rules: &Rules) -> Option<Exclusion> {
+    let parsed_url = parse_history_url(url)?;
+    let host = parsed_url.host_str()?;
+
+    if rules.excluded_domains.matches(host) {
+        return Some(Exclusion::Denylist);
+    }
+    if !rules.allowed_domains.is_empty()
+        && !rules.allowed_domains.matches(host)
+        && !rules.allowed_domains.matches(registrable_domain(host))
+    {
+        return Some(Exclusion::Allowlist);
+    }
+    None
+}
+
+pub fn extract_keywords(url: &str, rules: &Rules) -> Vec<String> {
+    match parse_history_url(url) {
+        Some(parsed_url) => url_keywords(&parsed_url, url, rules),
+        None => Vec::new(),
+    }
+}
+
+// extract_keywords for a URL already parsed by parse_history_url; `url` is the text it was
+// parsed from, which the extractors match against
+pub(crate) fn url_keywords(parsed_url: &Url, url: &str, rules: &Rules) -> Vec<String> {
+    if !rules.scheme_allowed(parsed_url.scheme()) {
+        debug!("skipping URL scheme `{}`", parsed_url.scheme());
+        return Vec::new();
+    }
+    if !rules.include_local && is_local_host(parsed_url) {
+        return Vec::new();
+    }
+    if parsed_url.host_str().is_some_and(|host| rules.excluded_domains.matches(host)) {
+        return Vec::new();
+    }
+
+    let domain = strip_subdomains(parsed_url.domain().unwrap_or(""), &rules.strip_subdomains);
+    let path_words: Vec<String> = parsed_url.path().split('/').map(decode_path_segment).collect();
+
+    let mut keywords: Vec<String> = domain.split('.')
+        .chain(path_words.iter().flat_map(|segment| segment.split_whitespace()))
+        .filter_map(|segment| {
+            let word = if rules.case_sensitive { segment.to_string() } else { segment.to_lowercase() };
+            // Networks are kept as spelled, `zk-lokomotive` included
+            let word = if rules.keywords.networks.contains(&word) { word } else { clean_token(&word, rules.keep_numeric)? };
+            let lowercase_word = word.to_lowercase();
+            if IGNORED_WORD_SET.contains(lowercase_word.as_str()) || rules.extra_ignored_words.contains(&lowercase_word) {
+                None
+            } else {
+                Some(word)
+            }
+        })
+        .collect();
+    keywords.extend(extractor_matches(url, rules));
+    keywords
+}
+
+// Strips everything but letters and digits, of any script, so emoji and punctuation (`—`,
+// `🚀solana!`) don't make it into the histogram. None for what is left empty or, unless
+// keep_numeric, is only digits (`123456`).
+fn clean_token(token: &str, keep_numeric: bool) -> Option<String> {
+    let cleaned: String = token.chars().filter(|c| c.is_alphanumeric()).collect();
+    if cleaned.is_empty() || (!keep_numeric && cleaned.chars().all(char::is_numeric)) {
+        None
+    } else {
+        Some(cleaned)
+    }
+}
+
+fn extractor_matches(url: &str, rules: &Rules) -> Vec<String> {
+    if rules.extractors.is_empty() {
+        return Vec::new();
+    }
+    let decoded = percent_decode_str(url).decode_utf8_lossy();
+    let mut matches = Vec::new();
+    for extractor in &rules.extractors {
+        for found in extractor.regex.find_iter(&decoded) {
+            trace!("extractor {} matched `{}`", extractor.name, found.as_str());
+            matches.push(found.as_str().to_string());
+        }
+    }
+    matches
+}
+
+// `/search/solana%20staking` and `/search/solana+staking` both become "solana staking". A segment
+// whose escapes don't decode to UTF-8 is kept as-is; malformed escapes like `%zz` pass through.
+fn decode_path_segment(segment: &str) -> String {
+    let spaced = segment.replace('+', " ");
+    match percent_decode_str(&spaced).decode_utf8() {
+        Ok(decoded) => decoded.into_owned(),
+        Err(_) => spaced,
+    }
+}
+
+
+// Adds one link's keywords to `counter`: every matching pattern label, and the keyword itself
+// when it is a network, longer than three characters or an extractor match
+pub(crate) fn count_keywords(keywords: &[String], rules: &Rules, counter: &mut HashMap<String, u32>) {
+    for word in keywords {
+        for label in rules.keywords.matching_labels(word) {
+            *counter.entry(label.to_string()).or_insert(0) += 1;
+        }
+
+        let extracted = rules.extractors.iter().any(|extractor| extractor.regex.is_match(word));
+        if rules.keywords.networks.contains(word) || word.len() > 3 || extracted {
+            *counter.entry(word.clone()).or_insert(0) += 1;
+        }
+    }
+}
+
+// A source of weighted words for a link: what the analyzer counts. KeywordAnalyzer, the URL
+// tokenizer, is registered by default; custom analyzers (HistoryAnalyzer::register_analyzer) add
+// their own notion of what is interesting. `title` is always None for now, as page titles are
+// not read from the history.
+pub trait Analyzer: Send + Sync {
+    fn name(&self) -> &str;
+    fn score(&self, url: &Url, title: Option<&str>) -> Vec<(String, f64)>;
+
+    // What the analyzer is given for a link: `keywords` are the URL's keywords, tokenized once for
+    // every analyzer. Scores the URL itself unless overridden.
+    fn score_keywords(&self, url: &Url, keywords: &[String], title: Option<&str>) -> Vec<(String, f64)> {
+        let _ = keywords;
+        self.score(url, title)
+    }
+}
+
+// The built-in analyzer: every keyword of the URL (and every matching keyword pattern label)
+// scores 1 per occurrence
+pub struct KeywordAnalyzer {
+    rules: Rules,
+}
+
+impl KeywordAnalyzer {
+    pub const NAME: &'static str = "keywords";
+
+    pub fn new(rules: Rules) -> KeywordAnalyzer {
+        KeywordAnalyzer { rules }
+    }
+}
+
+impl Analyzer for KeywordAnalyzer {
+    fn name(&self) -> &str {
+        KeywordAnalyzer::NAME
+    }
+
+    fn score(&self, url: &Url, title: Option<&str>) -> Vec<(String, f64)> {
+        self.score_keywords(url, &extract_keywords(url.as_str(), &self.rules), title)
+    }
+
+    fn score_keywords(&self, _url: &Url, keywords: &[String], _title: Option<&str>) -> Vec<(String, f64)> {
+        let mut counter = HashMap::new();
+        count_keywords(keywords, &self.rules, &mut counter);
+        counter.into_iter().map(|(word, count)| (word, f64::from(count))).collect()
+    }
+}
+
+// What a link adds to the word counts: every analyzer's scores summed per word and rounded to a
+// whole count, so a word needs a total score of at least 0.5 to be counted. `keywords` are the
+// URL's, from url_keywords.
+pub(crate) fn score_link(analyzers: &[Box<dyn Analyzer>], url: &Url, keywords: &[String], title: Option<&str>) -> HashMap<String, u32> {
+    let mut scores: HashMap<String, f64> = HashMap::new();
+    for analyzer in analyzers {
+        for (word, score) in analyzer.score_keywords(url, keywords, title) {
+            *scores.entry(word).or_insert(0.0) += score;
+        }
+    }
+    scores.into_iter()
+        .filter(|(_, score)| score.is_finite() && score.round() >= 1.0)
+        .map(|(word, score)| (word, score.round().min(f64::from(u32::MAX)) as u32))
+        .collect()
+}
+
+// Highest counts first; ties are broken alphabetically so output is reproducible
+pub(crate) fn top_words(counter: &HashMap<String, u32>, keywords: &Keywords, limit: usize) -> Vec<WordCount> {
+    let mut words: Vec<WordCount> = counter.iter()
+        .map(|(word, &count)| WordCount {
+            word: word.clone(),
+            count,
+            category: Some(keywords.category(word)),
+        })
+        .collect();
+    words.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.word.cmp(&b.word)));
+    words.truncate(limit);
+    words
+}
+
+// The recognized networks among the counted words, highest counts first like top_words, whatever
+// else was counted more often. Every entry is a network, so no category is attached.
+pub(crate) fn networks_summary(counter: &HashMap<String, u32>, keywords: &Keywords) -> Vec<WordCount> {
+    let mut networks: Vec<WordCount> = counter.iter()
+        .filter(|(word, _)| keywords.networks.contains(*word))
+        .map(|(word, &count)| WordCount { word: word.clone(), count, category: None })
+        .collect();
+    networks.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.word.cmp(&b.word)));
+    networks
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UrlAnalysis {
+    pub links_analyzed: usize,
+    pub excluded: usize,
+    pub top_words: Vec<WordCount>,
+    pub word_counts: HashMap<String, u32>,
+}
+
+// One-shot analysis of a list of URLs, as a batch of HistoryAnalyzer would count them: excluded
+// URLs are skipped and spelling variants of one page (see normalize_url) are counted once
+pub fn analyze_urls(urls: &[String], rules: &Rules, limit: usize) -> UrlAnalysis {
+    let mut seen = HashSet::new();
+    let mut word_counts = HashMap::new();
+    let (mut links_analyzed, mut excluded) = (0, 0);
+    for url in urls {
+        if exclusion(url, rules).is_some() {
+            excluded += 1;
+            continue;
+        }
+        if !seen.insert(normalize_url(url, &rules.tracking_params)) {
+            continue;
+        }
+        links_analyzed += 1;
+        count_keywords(&extract_keywords(url, rules), rules, &mut word_counts);
+    }
+    UrlAnalysis {
+        links_analyzed,
+        excluded,
+        top_words: top_words(&word_counts, &rules.keywords, limit),
+        word_counts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn analyze_urls_counts_each_page_once_and_skips_excluded_hosts() {
+        let urls: Vec<String> = [
+            "https://solana.com/staking",
+            "http://www.solana.com/staking?utm_source=x",
+            "https://solana.com/developers",
+            "http://localhost:3000/solana",
+            "chrome://settings",
+        ]
+        .iter()
+        .map(|url| url.to_string())
+        .collect();
+        let analysis = analyze_urls(&urls, &Rules::default(), 2);
+
+        assert_eq!(analysis.links_analyzed, 4);
+        assert_eq!(analysis.word_counts.get("solana"), Some(&2));
+        assert_eq!(analysis.word_counts.get("staking"), Some(&1));
+        assert_eq!(analysis.top_words[0].word, "solana");
+        assert_eq!(analysis.top_words[0].category, Some(KeywordCategory::Network));
+    }
+
+    #[test]
+    fn equivalent_spellings_normalize_to_one_key() {
+        let tracking_params: Vec<String> = DEFAULT_TRACKING_PARAMS.iter().map(|&p| p.to_string()).collect();
+        let table: [&[&str]; 7] = [
+            // scheme and host case
+            &["https://uniswap.org/swap", "HTTPS://Uniswap.ORG/swap", "http://uniswap.org/swap"],
+            // default port
+            &["https://uniswap.org/swap", "https://uniswap.org:443/swap", "http://uniswap.org:80/swap"],
+            // root trailing slash and duplicate slashes
+            &["https://uniswap.org", "https://uniswap.org/", "https://uniswap.org//"],
+            // fragment and tracking parameters
+            &["https://uniswap.org", "https://uniswap.org/#top", "https://uniswap.org/?utm_source=twitter#top", "https://uniswap.org/?gclid=1&ref=x"],
+            // query ordering
+            &["https://uniswap.org/swap?chain=1&token=eth", "https://uniswap.org/swap?token=eth&chain=1", "https://uniswap.org/swap?utm_medium=x&token=eth&chain=1"],
+            // www.
+            &["https://uniswap.org/swap", "https://www.uniswap.org/swap", "http://WWW.uniswap.org/swap#x"],
+            // repeated parameters keep their order
+            &["https://uniswap.org/?a=2&a=1&b=0", "https://uniswap.org/?b=0&a=2&a=1"],
+        ];
+        for spellings in table {
+            let key = normalize_url(spellings[0], &tracking_params);
+            for spelling in &spellings[1..] {
+                assert_eq!(normalize_url(spelling, &tracking_params), key, "{}", spelling);
+            }
+        }
+        assert_ne!(normalize_url("https://uniswap.org/?a=2&a=1", &tracking_params), normalize_url("https://uniswap.org/?a=1&a=2", &tracking_params));
+    }
+
+    #[test]
+    fn numeric_tokens_are_kept_on_request() {
+        let url = "https://example.com/2024/%E2%80%94";
+        assert_eq!(extract_keywords(url, &Rules::default()), ["example"]);
+        assert_eq!(extract_keywords(url, &Rules { keep_numeric: true, ..Rules::default() }), ["example", "2024"]);
+    }
+
+    #[test]
+    fn case_sensitive_rules_keep_tickers_apart() {
+        let url = "https://example.com/ETH/eth/Search/Staking";
+        let mut rules = Rules { keywords: Keywords::parse(r#"{"networks": ["ETH"]}"#).unwrap(), ..Rules::default() };
+        rules.keywords.lowercase_networks();
+        assert_eq!(extract_keywords(url, &rules), ["example", "eth", "eth", "staking"]);
+
+        let rules = Rules { keywords: Keywords::parse(r#"{"networks": ["ETH"]}"#).unwrap(), case_sensitive: true, ..Rules::default() };
+        assert_eq!(extract_keywords(url, &rules), ["example", "ETH", "eth", "Staking"]);
+        let mut counter = HashMap::new();
+        count_keywords(&extract_keywords(url, &rules), &rules, &mut counter);
+        assert_eq!(counter.get("ETH"), Some(&1));
+        assert_eq!(counter.get("eth"), None);
+    }
+
+    #[test]
+    fn overlapping_patterns_count_each_label_once_per_token() {
+        let patterns = r#"{"patterns": [
+            {"label": "dex", "pattern": "swap$"},
+            {"label": "dex", "pattern": ".*swap"},
+            {"label": "dex", "pattern": "^uni"},
+            {"label": "zk", "pattern": "^zk"},
+            {"label": "zk", "pattern": "zk.*"}
+        ]}"#;
+        let rules = Rules { keywords: Keywords::parse(patterns).unwrap(), ..Rules::default() };
+        assert_eq!(rules.keywords.matching_labels("uniswap"), HashSet::from(["dex"]));
+        assert_eq!(rules.keywords.matching_labels("zkswap"), HashSet::from(["dex", "zk"]));
+
+        let mut counter = HashMap::new();
+        count_keywords(&["uniswap".to_string()], &rules, &mut counter);
+        assert_eq!(counter.get("dex"), Some(&1));
+        count_keywords(&["zkswap".to_string()], &rules, &mut counter);
+        assert_eq!(counter.get("dex"), Some(&2));
+        assert_eq!(counter.get("zk"), Some(&1));
+    }
+
+    #[test]
+    fn networks_summary_leaves_out_generic_words() {
+        let counter: HashMap<String, u32> = [("docs", 9), ("solana", 3), ("ethereum", 5), ("polkadot", 3), ("swap", 7)]
+            .into_iter()
+            .map(|(word, count)| (word.to_string(), count))
+            .collect();
+        let summary: Vec<(String, u32)> = networks_summary(&counter, &Keywords::default()).into_iter().map(|entry| (entry.word, entry.count)).collect();
+        assert_eq!(summary, [("ethereum".to_string(), 5), ("polkadot".to_string(), 3), ("solana".to_string(), 3)]);
+    }
+
+    struct Sentiment;
+
+    impl Analyzer for Sentiment {
+        fn name(&self) -> &str {
+            "sentiment"
+        }
+
+        fn score(&self, url: &Url, _title: Option<&str>) -> Vec<(String, f64)> {
+            let mood = if url.path().contains("rug") { "negative" } else { "positive" };
+            vec![(mood.to_string(), 0.6), ("solana".to_string(), 0.4), ("noise".to_string(), 0.2)]
+        }
+    }
+
+    #[test]
+    fn analyzer_scores_are_summed_per_word() {
+        let analyzers: Vec<Box<dyn Analyzer>> = vec![Box::new(KeywordAnalyzer::new(Rules::default())), Box::new(Sentiment)];
+        let url = Url::parse("https://solana.com/rug/solana").unwrap();
+        let counts = score_link(&analyzers, &url, &extract_keywords(url.as_str(), &Rules::default()), None);
+
+        // 2 from the domain and the path, 2.4 with the sentiment score
+        assert_eq!(counts.get("solana"), Some(&2));
+        assert_eq!(counts.get("negative"), Some(&1));
+        assert_eq!(counts.get("noise"), None);
+        assert_eq!(counts.get("positive"), None);
+    }
+
+    #[test]
+    fn domains_group_by_public_suffix() {
+        assert_eq!(registrable_domain("www.example.co.uk"), "example.co.uk");
+        assert_eq!(registrable_domain("en.m.wikipedia.org"), "wikipedia.org");
+        assert_eq!(registrable_domain("192.168.1.10"), "192.168.1.10");
+        assert_eq!(organization("www.example.co.uk"), "example");
+        assert_eq!(organization("example.com"), "example");
+        assert_eq!(organization("app.binance.us"), "binance");
+        assert_eq!(organization("localhost"), "localhost");
+    }
+}
@@ -0,0 +1,133 @@
+use std::fs;
+use std::io::Cursor;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::compression::{zk_compress, CompressionBackend};
+use crate::digest::canonical_payload;
+use crate::encoding::Encoding;
+use crate::error::AnalyzerError;
+use crate::output::OutputFormat;
+use crate::Config;
+
+// What `--format msgpack` / `--format cbor` writes for every result: the envelope as is, plus the
+// digest of the compressed payload as 32 raw bytes instead of an encoded string
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BinaryDocument {
+    #[serde(with = "serde_bytes")]
+    pub digest: Vec<u8>,
+    pub envelope: Value,
+}
+
+impl BinaryDocument {
+    // The JSON equivalent shows the digest in the envelope's own encoding
+    pub fn to_json(&self) -> Value {
+        let encoding: Encoding = serde_json::from_value(self.envelope["encoding"].clone()).unwrap_or_default();
+        json!({ "digest": encoding.encode(&self.digest), "envelope": self.envelope })
+    }
+}
+
+// SHA-256 of the same compressed payload emit_result puts on-chain, which never includes the
+// compression stats measured on it or the signature added afterwards
+pub fn payload_digest(envelope: &Value) -> [u8; 32] {
+    let encoding: Encoding = serde_json::from_value(envelope["encoding"].clone()).unwrap_or_default();
+    let compression: CompressionBackend = serde_json::from_value(envelope["compression"].clone()).unwrap_or_default();
+    let payload = canonical_payload(envelope).unwrap_or_else(|_| envelope.to_string());
+    let compressed = zk_compress(&payload, compression, encoding).expect("compressing into memory cannot fail");
+    Sha256::digest(compressed.payload.as_bytes()).into()
+}
+
+pub fn encode(format: OutputFormat, envelope: &Value) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let document = BinaryDocument { digest: payload_digest(envelope).to_vec(), envelope: envelope.clone() };
+    match format {
+        OutputFormat::MessagePack => Ok(rmp_serde::to_vec_named(&document)?),
+        OutputFormat::Cbor => {
+            let mut bytes = Vec::new();
+            ciborium::ser::into_writer(&document, &mut bytes).map_err(|e| format!("CBOR encoding failed: {:?}", e))?;
+            Ok(bytes)
+        }
+        other => Err(format!("{:?} is not a binary format", other).into()),
+    }
+}
+
+// Both formats are self-delimiting, so an appended file is simply documents back to back
+pub fn decode_all(format: OutputFormat, bytes: &[u8]) -> Result<Vec<BinaryDocument>, Box<dyn std::error::Error>> {
+    let mut reader = Cursor::new(bytes);
+    let mut documents = Vec::new();
+    while (reader.position() as usize) < bytes.len() {
+        documents.push(match format {
+            OutputFormat::MessagePack => rmp_serde::from_read(&mut reader)?,
+            OutputFormat::Cbor => {
+                ciborium::de::from_reader(&mut reader).map_err(|e| format!("Invalid CBOR document: {:?}", e))?
+            }
+            other => return Err(format!("{:?} is not a binary format", other).into()),
+        });
+    }
+    Ok(documents)
+}
+
+// Backs the `decode` subcommand: prints every document of a msgpack/cbor file as JSON. The format
+// comes from --format or else the file extension.
+pub fn run_decode(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config.decode_input.as_ref().ok_or_else(|| AnalyzerError::Usage("decode requires a file path".to_string()))?;
+    let format = match config.format {
+        Some(format) if format.is_binary() => format,
+        Some(_) => return Err(AnalyzerError::Usage("decode reads msgpack or cbor files".to_string()).into()),
+        None => match path.extension().and_then(|extension| extension.to_str()) {
+            Some("msgpack" | "mpk") => OutputFormat::MessagePack,
+            Some("cbor") => OutputFormat::Cbor,
+            _ => return Err(AnalyzerError::Usage(format!("Cannot tell the format of {}, pass --format msgpack or cbor", path.display())).into()),
+        },
+    };
+
+    let documents = fs::read(path)
+        .map_err(|e| e.into())
+        .and_then(|bytes| decode_all(format, &bytes))
+        .map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e)))?;
+    for document in documents {
+        println!("{}", serde_json::to_string_pretty(&document.to_json())?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_documents_match_json_field_by_field() {
+        let envelope = json!({
+            "schema_version": 2,
+            "generated_at": "2024-05-01T12:00:00Z",
+            "hostname": "ünïcödé-host",
+            "encoding": "base58",
+            "result": {
+                "most_common_word": "solana",
+                "count": 3,
+                "timestamp": -1,
+                "top_words": [{ "word": "solana", "count": 3, "category": "network" }],
+                "top_words_tfidf": [{ "word": "swap", "score": 0.25 }],
+                "history": null,
+            }
+        });
+        let from_json: Value = serde_json::from_str(&OutputFormat::Json.render(&envelope).unwrap()).unwrap();
+
+        for format in [OutputFormat::MessagePack, OutputFormat::Cbor] {
+            // Two documents back to back, as an appended output file would hold them
+            let mut bytes = encode(format, &envelope).unwrap();
+            bytes.extend(encode(format, &envelope).unwrap());
+            let documents = decode_all(format, &bytes).unwrap();
+            assert_eq!(documents.len(), 2);
+
+            for document in documents {
+                assert_eq!(document.digest, payload_digest(&from_json));
+                for (field, value) in from_json.as_object().unwrap() {
+                    assert_eq!(&document.envelope[field], value, "{:?} field {}", format, field);
+                }
+                assert_eq!(document.envelope.as_object().unwrap().len(), from_json.as_object().unwrap().len());
+            }
+        }
+    }
+}
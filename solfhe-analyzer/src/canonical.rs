@@ -0,0 +1,172 @@
+use std::fmt::Write;
+
+use serde_json::Value;
+
+// Canonical JSON in the style of RFC 8785 (JCS), the form every digest, signature and HMAC covers
+// from canonicalization version 2 on. The bytes depend only on the value, never on how it was
+// built: no whitespace, object keys sorted by their UTF-16 code units, strings with only the
+// mandatory escapes, and floats in ECMAScript's shortest form (`1` rather than `1.0`, `1e+21`,
+// `0.000001`). Integers are written as they are, which is the ECMAScript form for every integer
+// up to 2^53; nothing the analyzer writes comes close. Human-facing output stays pretty-printed.
+pub fn canonical_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(flag) => out.push_str(if *flag { "true" } else { "false" }),
+        Value::Number(number) => {
+            if let Some(int) = number.as_i64() {
+                write!(out, "{}", int).expect("writing to a String never fails");
+            } else if let Some(int) = number.as_u64() {
+                write!(out, "{}", int).expect("writing to a String never fails");
+            } else {
+                write_float(out, number.as_f64().expect("a JSON number is an integer or a finite float"));
+            }
+        }
+        // serde_json escapes exactly what JCS does: `"`, `\`, and control characters, the common
+        // ones as \b \f \n \r \t and the rest as lowercase \u00xx
+        Value::String(text) => out.push_str(&serde_json::to_string(text).expect("a string always serializes")),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, item);
+            }
+            out.push(']');
+        }
+        Value::Object(fields) => {
+            let mut entries: Vec<(&String, &Value)> = fields.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key).expect("a string always serializes"));
+                out.push(':');
+                write_value(out, item);
+            }
+            out.push('}');
+        }
+    }
+}
+
+// ECMAScript's Number::toString: the shortest digits that round-trip (Rust's `{:e}` finds the
+// same ones), placed by the decimal exponent n so that the value is 0.DIGITS × 10^n
+fn write_float(out: &mut String, value: f64) {
+    if value == 0.0 {
+        // -0 included
+        out.push('0');
+        return;
+    }
+    if value < 0.0 {
+        out.push('-');
+    }
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').expect("{:e} always writes an exponent");
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().expect("{:e} writes an integer exponent") + 1;
+
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        let (int, fraction) = digits.split_at(n as usize);
+        write!(out, "{}.{}", int, fraction).expect("writing to a String never fails");
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', -n as usize));
+        out.push_str(&digits);
+    } else {
+        let (first, rest) = digits.split_at(1);
+        out.push_str(first);
+        if !rest.is_empty() {
+            write!(out, ".{}", rest).expect("writing to a String never fails");
+        }
+        write!(out, "e{}{}", if n > 0 { '+' } else { '-' }, (n - 1).abs()).expect("writing to a String never fails");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    #[test]
+    fn numbers_strings_and_keys_follow_jcs() {
+        let floats = [
+            (1.0, "1"),
+            (-0.0, "0"),
+            (4.5, "4.5"),
+            (0.002, "0.002"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (1e21, "1e+21"),
+            (1e20, "100000000000000000000"),
+            (-1.5e30, "-1.5e+30"),
+            (333333333.3333333, "333333333.3333333"),
+        ];
+        for (value, expected) in floats {
+            assert_eq!(canonical_json(&json!(value)), expected, "{}", value);
+        }
+        assert_eq!(canonical_json(&json!(-42)), "-42");
+        assert_eq!(canonical_json(&json!(u64::MAX)), u64::MAX.to_string());
+        assert_eq!(canonical_json(&json!("€\u{1}\n\"/")), r#""€\u0001\n\"/""#);
+        // U+FF61 sorts before U+1F600 in UTF-8 but after it in UTF-16, where 😀 is a surrogate pair
+        assert_eq!(canonical_json(&json!({ "｡": 2, "😀": 1, "b": [true, null], "a": {} })), r#"{"a":{},"b":[true,null],"😀":1,"｡":2}"#);
+    }
+
+    proptest! {
+        // However the result text orders its keys, it canonicalizes (and therefore hashes) the same.
+        // serde_json's map keeps keys in UTF-8 order, so the ｡ and 😀 keys are what make
+        // canonical_json's own UTF-16 sort matter.
+        #[test]
+        fn insertion_order_never_changes_the_digest(
+            counts in prop::collection::hash_map("[a-zé｡😀]{1,8}", (any::<u32>(), -1e9f64..1e9), 0..24),
+            seed in any::<u64>(),
+        ) {
+            let entries: Vec<_> = counts.into_iter().collect();
+            let mut shuffled = entries.clone();
+            let mut state = seed | 1;
+            for i in (1..shuffled.len()).rev() {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                shuffled.swap(i, (state % (i as u64 + 1)) as usize);
+            }
+
+            // Raw result text with the keys in the given order, values written as canonical_json would
+            let object = |entries: &[(String, (u32, f64))], value: &dyn Fn(&(u32, f64)) -> String| {
+                let fields: Vec<String> = entries.iter().map(|(word, pair)| format!("{}:{}", json!(word), value(pair))).collect();
+                format!("{{{}}}", fields.join(","))
+            };
+            let count = |(count, _): &(u32, f64)| count.to_string();
+            let share = |(_, share): &(u32, f64)| canonical_json(&json!(share));
+            let text = |entries: &[(String, (u32, f64))]| {
+                format!(r#"{{"shares":{},"word_counts":{}}}"#, object(entries, &share), object(entries, &count))
+            };
+            let canonical = |entries: &[(String, (u32, f64))]| {
+                let value: Value = serde_json::from_str(&format!(r#"{{"result":{}}}"#, text(entries))).unwrap();
+                canonical_json(&value)
+            };
+            let original = canonical(&entries);
+            prop_assert_eq!(&original, &canonical(&shuffled));
+
+            let mut sorted = entries.clone();
+            sorted.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            prop_assert_eq!(&original, &format!(r#"{{"result":{}}}"#, text(&sorted)));
+
+            // And a verifier parsing the canonical bytes gets them back unchanged
+            let parsed: Value = serde_json::from_str(&original).unwrap();
+            prop_assert_eq!(canonical_json(&parsed), original);
+        }
+    }
+}
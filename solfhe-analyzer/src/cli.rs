@@ -0,0 +1,347 @@
+use std::path::PathBuf;
+
+use clap::{ArgAction, Args, Parser, Subcommand};
+
+// The command line. Values are kept as given and parsed by Config::apply_cli, so a bad value
+// gets the same message as the same setting in the config file. Options are global: they can
+// come before or after the command.
+#[derive(Debug, Parser)]
+#[command(name = "solfhe-analyzer", version, about = "Analyze the browser history for blockchain interests", after_help = EXIT_CODES)]
+pub(crate) struct Cli {
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+    #[command(flatten)]
+    pub(crate) options: Options,
+}
+
+const EXIT_CODES: &str = "\
+Exit codes (--once, export, scan, merge, decode, the verify and prove commands, decrypt, rekey and report):
+  0   success
+  1   any other error
+  2   no browser history source found
+  3   reading the history or an input file failed
+  4   writing the output failed
+  5   a result signature or HMAC tag is missing or invalid, or a payload does not match its result
+  6   an encrypted result could not be decrypted
+  7   a top-keyword or inclusion proof is missing or invalid
+  64  bad command line usage or config file";
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Command {
+    /// Poll the browser history and emit a result per batch (the default)
+    #[command(alias = "watch")]
+    Run,
+    /// Analyze the current history once and write a single result
+    Export {
+        /// json, jsonl, ndjson, toml, csv, pretty, msgpack or cbor
+        format: String,
+    },
+    /// Analyze the whole history once (see --report)
+    Scan,
+    /// Query previously stored batches: list [N], show ID or top [DAYS]
+    #[command(alias = "results")]
+    Query {
+        #[arg(value_parser = ["list", "show", "top"])]
+        action: String,
+        argument: Option<String>,
+    },
+    /// Add up the keyword counts of several result files
+    Merge { files: Vec<PathBuf> },
+    /// Print a msgpack or cbor result file as JSON
+    Decode { file: PathBuf },
+    /// Check the signatures of a JSON or JSON Lines result file, or with ORIGINAL that the
+    /// compressed on-chain payload in FILE was made from the JSON result ORIGINAL
+    Verify { file: PathBuf, original: Option<PathBuf> },
+    /// Check the top-keyword proofs of a result file (see --prove)
+    VerifyProof { file: PathBuf },
+    /// Prove that URL was or wasn't in a stored batch (see --batch)
+    ProveInclusion { url: String },
+    /// Check an inclusion proof against the results database
+    VerifyInclusion { file: PathBuf },
+    /// Summarize every batch in the results database
+    Report,
+    /// List the results stored for WALLET by the registry program (see --program-id)
+    OnchainResults { wallet: Option<String> },
+    /// Compare the digests of stored batches with the memos and result accounts they were
+    /// submitted to (all batches, --batch ID, or --batch ID --to-batch ID)
+    VerifyOnchain,
+    /// The --keypair wallet on the selected cluster
+    Wallet {
+        #[command(subcommand)]
+        action: WalletCommand,
+    },
+    /// Check why no results come out: home directory, history file, SQLite and URLs
+    Doctor,
+    /// Print the results of an encrypted JSON or JSON Lines file in the clear
+    Decrypt { file: PathBuf },
+    /// Change the encryption passphrase (see --new-passphrase-file)
+    Rekey,
+    /// Train a zstd dictionary on a JSON Lines result file for --compression zstd
+    TrainDict { file: PathBuf },
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum WalletCommand {
+    /// Print the wallet's address, balance and cluster
+    Status,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct Options {
+    /// Poll the history once, emit what was found and exit
+    #[arg(long, global = true)]
+    pub(crate) once: bool,
+    /// Analyze the whole history (since --since) into a baseline result before polling
+    #[arg(long, global = true)]
+    pub(crate) backfill: bool,
+    /// With --backfill and scan, skip visits before DATE (YYYY-MM-DD or RFC 3339)
+    #[arg(long, global = true, value_name = "DATE")]
+    pub(crate) since: Option<String>,
+    /// Read settings from a TOML file; flags override it
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) config: Option<PathBuf>,
+    /// json, jsonl, ndjson, toml, csv, pretty, msgpack or cbor
+    #[arg(long, global = true)]
+    pub(crate) format: Option<String>,
+    /// Seconds between analysis cycles
+    #[arg(long, global = true, value_name = "SECS")]
+    pub(crate) interval_secs: Option<u64>,
+    /// Number of new links that make up a batch
+    #[arg(long, global = true, value_name = "N")]
+    pub(crate) batch_size: Option<usize>,
+    /// URLs read from the history per cycle (0 for no limit)
+    #[arg(long, global = true, value_name = "N")]
+    pub(crate) history_limit: Option<u32>,
+    /// Read this Chrome-compatible SQLite file instead of the browser history
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) history_path: Option<PathBuf>,
+    /// Read every visit from Chrome's visits table instead of one row per URL
+    #[arg(long, global = true)]
+    pub(crate) use_visits_table: bool,
+    /// Retries of a missing or unreadable history (0 to fail at once)
+    #[arg(long, global = true, value_name = "N")]
+    pub(crate) history_retries: Option<u32>,
+    /// First wait between history retries, doubled after each
+    #[arg(long, global = true, value_name = "MS")]
+    pub(crate) history_retry_delay_ms: Option<u64>,
+    /// Keep the case of path words (ETH and eth differ) and match networks exactly
+    #[arg(long, global = true)]
+    pub(crate) case_sensitive: bool,
+    /// Keep path words that are only digits, like ids and years
+    #[arg(long, global = true)]
+    pub(crate) keep_numeric: bool,
+    /// Also tokenize localhost, loopback and private network URLs
+    #[arg(long, global = true)]
+    pub(crate) include_local: bool,
+    /// JSON keywords file with networks and labelled patterns
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) keywords: Option<PathBuf>,
+    /// Only analyze the domains listed in FILE
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) allow_domains: Vec<PathBuf>,
+    /// Never analyze the domains listed in FILE
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) exclude_domains: Vec<PathBuf>,
+    /// Redact the value of this query parameter as soon as a URL is read
+    #[arg(long, global = true, value_name = "NAME")]
+    pub(crate) redact_param: Vec<String>,
+    /// Treat a query parameter as tracking noise when deduplicating URLs
+    #[arg(long, global = true, value_name = "NAME")]
+    pub(crate) strip_param: Vec<String>,
+    /// Drop a leading host label before tokenizing
+    #[arg(long, global = true, value_name = "LABEL")]
+    pub(crate) strip_subdomain: Vec<String>,
+    /// Tokenize URLs of this scheme too
+    #[arg(long, global = true, value_name = "NAME")]
+    pub(crate) scheme: Vec<String>,
+    /// Never tokenize URLs of this scheme
+    #[arg(long, global = true, value_name = "NAME")]
+    pub(crate) exclude_scheme: Vec<String>,
+    /// Weigh words by TF-IDF against a background corpus
+    #[arg(long, global = true)]
+    pub(crate) tfidf: bool,
+    /// Also write results to FILE
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) output: Option<PathBuf>,
+    /// Append results to --output as JSON lines
+    #[arg(long, global = true)]
+    pub(crate) append: bool,
+    /// Rotate FILE to FILE.1, FILE.2, ... before it grows past BYTES
+    #[arg(long, global = true, visible_alias = "max-output-size", value_name = "BYTES")]
+    pub(crate) output_max_size: Option<u64>,
+    /// Keep N rotated files (default 1)
+    #[arg(long, global = true, value_name = "N")]
+    pub(crate) output_keep: Option<usize>,
+    /// POST every result to URL
+    #[arg(long, global = true, value_name = "URL")]
+    pub(crate) webhook: Option<String>,
+    #[arg(long, global = true, value_name = "SECS")]
+    pub(crate) webhook_timeout_secs: Option<u64>,
+    /// Upload only per-network and per-category counts to URL (off by default)
+    #[arg(long, global = true, value_name = "URL")]
+    pub(crate) share_aggregates: Option<String>,
+    /// With scan, write a Markdown report to FILE
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) report: Option<PathBuf>,
+    /// Leave the per-category sections out of the report
+    #[arg(long, global = true)]
+    pub(crate) report_no_categories: bool,
+    /// Print a summary of the session to stderr when the analyzer stops
+    #[arg(long, global = true)]
+    pub(crate) profile_report: bool,
+    /// Add per-URL details to every result
+    #[arg(long, global = true)]
+    pub(crate) detail: bool,
+    /// Show raw URLs in the details instead of salted hashes
+    #[arg(long, global = true)]
+    pub(crate) detail_raw_urls: bool,
+    /// Leave the hostname and domains out of results
+    #[arg(long, global = true)]
+    pub(crate) anonymize: bool,
+    /// Salt of the URL hashes instead of the generated one
+    #[arg(long, global = true)]
+    pub(crate) salt: Option<String>,
+    #[arg(long, global = true, value_name = "N")]
+    pub(crate) cooccurrence_top: Option<usize>,
+    /// Add the change of every word since the previous result
+    #[arg(long, global = true)]
+    pub(crate) deltas: bool,
+    /// Add how long reading the history and analyzing it took
+    #[arg(long, global = true)]
+    pub(crate) timings: bool,
+    /// Add a histogram of the sites behind a batch
+    #[arg(long, global = true)]
+    pub(crate) domains: bool,
+    #[arg(long, global = true, value_name = "N")]
+    pub(crate) max_report_words: Option<usize>,
+    #[arg(long, global = true, value_name = "N")]
+    pub(crate) max_tracked_words: Option<usize>,
+    /// Batch by browsing session instead of link count
+    #[arg(long, global = true)]
+    pub(crate) sessions: bool,
+    #[arg(long, global = true, value_name = "MINS")]
+    pub(crate) session_gap_mins: Option<u64>,
+    /// Include the previous N batches in every result
+    #[arg(long, global = true, value_name = "N")]
+    pub(crate) history: Option<usize>,
+    /// How many analyzed links are remembered for deduplication (0 for unbounded)
+    #[arg(long, global = true, value_name = "N")]
+    pub(crate) seen_capacity: Option<usize>,
+    /// Show a desktop notification when the top word changes
+    #[arg(long, global = true)]
+    pub(crate) notify: bool,
+    #[arg(long, global = true)]
+    pub(crate) no_compression_stats: bool,
+    /// Homomorphic keyword counters: decrypt (the default) or emit
+    #[arg(long, global = true, value_name = "MODE", num_args = 0..=1, default_missing_value = "decrypt")]
+    pub(crate) fhe: Option<String>,
+    /// sha256 or poseidon
+    #[arg(long, global = true)]
+    pub(crate) hash: Option<String>,
+    /// digest, deflate or zstd
+    #[arg(long, global = true)]
+    pub(crate) compression: Option<String>,
+    /// base64, base64url, base58 or hex
+    #[arg(long, global = true)]
+    pub(crate) encoding: Option<String>,
+    /// Short for --encoding base64url
+    #[arg(long, global = true)]
+    pub(crate) urlsafe: bool,
+    /// Forget the persisted state and start over
+    #[arg(long, global = true)]
+    pub(crate) reset_state: bool,
+    /// Encrypt the result of every emitted envelope (see SOLFHE_ENCRYPTION_KEY)
+    #[arg(long, global = true)]
+    pub(crate) encrypt: bool,
+    /// Derive the encryption key from the passphrase in FILE instead of prompting
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) passphrase_file: Option<PathBuf>,
+    /// With rekey, the file holding the new passphrase
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) new_passphrase_file: Option<PathBuf>,
+    /// Sign results with this Solana keypair file instead of the generated key
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) signing_key: Option<PathBuf>,
+    /// Anchor every result's digest on Solana with a Memo transaction
+    #[arg(long, global = true, value_name = "solana")]
+    pub(crate) submit: Option<String>,
+    /// Pay for --submit with this keypair file (default: $SOLANA_KEYPAIR)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) keypair: Option<PathBuf>,
+    /// Solana cluster: devnet, testnet, mainnet-beta or localnet (the default)
+    #[arg(long, global = true, value_name = "CLUSTER")]
+    pub(crate) cluster: Option<String>,
+    /// Solana RPC endpoint instead of the cluster's public one
+    #[arg(long, global = true, value_name = "URL")]
+    pub(crate) rpc_url: Option<String>,
+    /// processed, confirmed or finalized (the default)
+    #[arg(long, global = true, value_name = "LEVEL")]
+    pub(crate) commitment: Option<String>,
+    /// Give up on an RPC request after SECS (default 30)
+    #[arg(long, global = true, value_name = "SECS")]
+    pub(crate) rpc_timeout_secs: Option<u64>,
+    /// Priority fee of submissions in micro-lamports per compute unit, or auto to pay the recent median
+    #[arg(long, global = true, value_name = "MICRO_LAMPORTS")]
+    pub(crate) priority_fee: Option<String>,
+    /// With --submit, also store every digest in an account of this registry program
+    #[arg(long, global = true, value_name = "ID")]
+    pub(crate) program_id: Option<String>,
+    /// With verify, require results to be signed by KEY
+    #[arg(long, global = true, value_name = "KEY")]
+    pub(crate) public_key: Option<String>,
+    /// With verify, reject results whose batch counter doesn't exceed the highest one verified
+    /// before from the same key (replays)
+    #[arg(long, global = true)]
+    pub(crate) track_counters: bool,
+    /// Tag results with the first HMAC key in FILE (one per line)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) hmac_key_file: Option<PathBuf>,
+    /// With verify, check HMAC tags against every key instead of signatures
+    #[arg(long, global = true)]
+    pub(crate) hmac: bool,
+    /// Add a zero-knowledge proof of the top keyword to every result
+    #[arg(long, global = true)]
+    pub(crate) prove: bool,
+    /// With verify-proof, use this key instead of the one generated here
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) verifying_key: Option<PathBuf>,
+    /// With prove-inclusion, use this batch instead of the latest; with verify-onchain, the
+    /// (first) batch to verify
+    #[arg(long, global = true, value_name = "ID")]
+    pub(crate) batch: Option<i64>,
+    /// With verify-onchain, the last batch to verify
+    #[arg(long, global = true, value_name = "ID")]
+    pub(crate) to_batch: Option<i64>,
+    /// Short for --log-level debug
+    #[arg(long, global = true)]
+    pub(crate) debug: bool,
+    /// error, warn, info, debug or trace
+    #[arg(long, global = true, value_name = "LEVEL")]
+    pub(crate) log_level: Option<String>,
+    /// Log JSON lines instead of text
+    #[arg(long, global = true)]
+    pub(crate) log_json: bool,
+    /// Log at debug (-v) or trace (-vv) level
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub(crate) verbose: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn options_are_accepted_before_and_after_the_command() {
+        Cli::command().debug_assert();
+        let cli = Cli::try_parse_from(["solfhe-analyzer", "--output", "out.json", "watch", "-vv", "--fhe"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Run)));
+        assert_eq!(cli.options.output, Some(PathBuf::from("out.json")));
+        assert_eq!((cli.options.verbose, cli.options.fhe.as_deref()), (2, Some("decrypt")));
+
+        let cli = Cli::try_parse_from(["solfhe-analyzer", "results", "list", "3", "--max-output-size", "10"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Query { action, argument }) if action == "list" && argument.as_deref() == Some("3")));
+        assert_eq!(cli.options.output_max_size, Some(10));
+        assert!(Cli::try_parse_from(["solfhe-analyzer", "--no-such-flag"]).is_err());
+    }
+}
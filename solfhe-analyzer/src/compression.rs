@@ -0,0 +1,523 @@
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tracing::{info, trace, warn};
+
+use crate::encoding::Encoding;
+use crate::error::AnalyzerError;
+use crate::state::state_dir;
+use crate::Config;
+
+// Backend id byte plus the original length as a little-endian u32
+const HEADER_LEN: usize = 5;
+const ZSTD_LEVEL: i32 = 19;
+// Payload header id of zstd with a trained dictionary; the body starts with the dictionary's hash
+const ZSTD_DICTIONARY_ID: u8 = 4;
+// Result envelopes share most of their keys, so a few KiB of dictionary covers them
+const DICTIONARY_SIZE: usize = 16 * 1024;
+// Below this zstd can't train anything useful (and often refuses to)
+const MIN_TRAINING_SAMPLES: usize = 20;
+// Leading hex characters of the dictionary hash shown in the envelope
+const DICTIONARY_HASH_LEN: usize = 16;
+
+// One way of shrinking a result payload. Backends only see the body; the header that lets
+// zk_decompress pick the right backend is added around them.
+pub trait Compressor {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError>;
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError>;
+}
+
+// Only the SHA-256 of the data: what the original scheme amounted to. Proves a result existed but
+// can't be turned back into it.
+pub struct DigestOnly;
+
+pub struct Deflate;
+
+pub struct Zstd;
+
+impl Compressor for DigestOnly {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        Ok(Sha256::digest(data).to_vec())
+    }
+
+    fn decompress(&self, _data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        Err(CompressionError::Irreversible)
+    }
+}
+
+impl Compressor for Deflate {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(data).and_then(|()| encoder.finish()).map_err(|e| CompressionError::Compress(e.to_string()))
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        let mut inflated = Vec::new();
+        DeflateDecoder::new(data).read_to_end(&mut inflated).map_err(|e| CompressionError::Corrupted(e.to_string()))?;
+        Ok(inflated)
+    }
+}
+
+impl Compressor for Zstd {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        zstd::encode_all(data, ZSTD_LEVEL).map_err(|e| CompressionError::Compress(e.to_string()))
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        zstd::decode_all(data).map_err(|e| CompressionError::Corrupted(e.to_string()))
+    }
+}
+
+// A zstd dictionary trained on past results by `train-dict`
+pub struct ZstdDictionary {
+    bytes: Vec<u8>,
+    sha256: [u8; 32],
+}
+
+impl ZstdDictionary {
+    pub fn new(bytes: Vec<u8>) -> ZstdDictionary {
+        let sha256 = Sha256::digest(&bytes).into();
+        ZstdDictionary { bytes, sha256 }
+    }
+
+    fn path() -> PathBuf {
+        state_dir().join("zstd.dict")
+    }
+
+    // Short form for the envelope and logs; payloads carry the full hash
+    pub fn hash(&self) -> String {
+        let mut hash = hex::encode(self.sha256);
+        hash.truncate(DICTIONARY_HASH_LEN);
+        hash
+    }
+}
+
+// Loaded once per process. When present, every zstd payload is compressed with it.
+static DICTIONARY: Lazy<Option<ZstdDictionary>> = Lazy::new(|| match fs::read(ZstdDictionary::path()) {
+    Ok(bytes) => Some(ZstdDictionary::new(bytes)),
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+    Err(e) => {
+        warn!("ignoring unreadable zstd dictionary {}: {}", ZstdDictionary::path().display(), e);
+        None
+    }
+});
+
+// The dictionary zk_compress will use for `backend`, if any
+pub fn active_dictionary(backend: CompressionBackend) -> Option<&'static ZstdDictionary> {
+    match backend {
+        CompressionBackend::Zstd => DICTIONARY.as_ref(),
+        _ => None,
+    }
+}
+
+// zstd with a dictionary. The body is prefixed with the dictionary's SHA-256, so a payload is
+// only ever decompressed with the dictionary it was compressed with.
+pub struct ZstdWithDictionary<'a>(pub &'a ZstdDictionary);
+
+impl Compressor for ZstdWithDictionary<'_> {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        let frame = zstd::bulk::Compressor::with_dictionary(ZSTD_LEVEL, &self.0.bytes)
+            .and_then(|mut compressor| compressor.compress(data))
+            .map_err(|e| CompressionError::Compress(e.to_string()))?;
+        let mut body = self.0.sha256.to_vec();
+        body.extend(frame);
+        Ok(body)
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        if data.len() < self.0.sha256.len() {
+            return Err(CompressionError::Truncated);
+        }
+        let (hash, frame) = data.split_at(self.0.sha256.len());
+        if hash != self.0.sha256 {
+            return Err(CompressionError::DictionaryMismatch { expected: short_hash(hash), found: Some(self.0.hash()) });
+        }
+        let mut decompressed = Vec::new();
+        zstd::stream::read::Decoder::with_dictionary(frame, &self.0.bytes)
+            .and_then(|mut decoder| decoder.read_to_end(&mut decompressed))
+            .map_err(|e| CompressionError::Corrupted(e.to_string()))?;
+        Ok(decompressed)
+    }
+}
+
+fn short_hash(hash: &[u8]) -> String {
+    let mut hash = hex::encode(hash);
+    hash.truncate(DICTIONARY_HASH_LEN);
+    hash
+}
+
+// Selected with --compression and recorded in the envelope's `compression` field. The id is the
+// payload's first byte; deflate keeps 1 so payloads from before the backends existed still decode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionBackend {
+    Digest,
+    #[default]
+    Deflate,
+    Zstd,
+}
+
+impl CompressionBackend {
+    fn id(&self) -> u8 {
+        match self {
+            CompressionBackend::Deflate => 1,
+            CompressionBackend::Zstd => 2,
+            CompressionBackend::Digest => 3,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<CompressionBackend> {
+        [CompressionBackend::Digest, CompressionBackend::Deflate, CompressionBackend::Zstd]
+            .into_iter()
+            .find(|backend| backend.id() == id)
+    }
+
+    pub fn compressor(&self) -> &'static dyn Compressor {
+        match self {
+            CompressionBackend::Digest => &DigestOnly,
+            CompressionBackend::Deflate => &Deflate,
+            CompressionBackend::Zstd => &Zstd,
+        }
+    }
+
+    pub fn is_reversible(&self) -> bool {
+        *self != CompressionBackend::Digest
+    }
+}
+
+impl FromStr for CompressionBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "digest" => Ok(CompressionBackend::Digest),
+            "deflate" => Ok(CompressionBackend::Deflate),
+            "zstd" => Ok(CompressionBackend::Zstd),
+            other => Err(format!("Unknown compression: {} (expected digest, deflate or zstd)", other)),
+        }
+    }
+}
+
+impl fmt::Display for CompressionBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CompressionBackend::Digest => "digest",
+            CompressionBackend::Deflate => "deflate",
+            CompressionBackend::Zstd => "zstd",
+        })
+    }
+}
+
+// What goes on-chain for one result: the encoded `backend || length || body` payload, plus the
+// SHA-256 of the original data to check a round trip against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedPayload {
+    pub payload: String,
+    pub sha256: String,
+}
+
+// Why a payload could not be produced or turned back into its original string. Library callers
+// match on the variant; the CLI maps each to an exit code, see exit_code.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CompressionError {
+    // The backend failed to compress
+    #[error("compression failed: {0}")]
+    Compress(String),
+    // Not valid text in the payload's encoding
+    #[error("payload is not validly encoded: {0}")]
+    Encoding(String),
+    // Shorter than the header
+    #[error("payload is shorter than its header")]
+    Truncated,
+    #[error("unknown compression backend {0}")]
+    UnknownBackend(u8),
+    // A digest-only payload has nothing to decompress
+    #[error("payload only holds a digest and cannot be decompressed")]
+    Irreversible,
+    // The compressed body is corrupted
+    #[error("corrupted compressed payload: {0}")]
+    Corrupted(String),
+    // The payload was compressed with a zstd dictionary other than the one available (`found` is
+    // None when there is none)
+    #[error("payload needs zstd dictionary {expected}, but {}", local_dictionary(.found))]
+    DictionaryMismatch { expected: String, found: Option<String> },
+    // The body decompressed fine but to a different length than the header records
+    #[error("payload decompressed to {actual} bytes, header says {expected}")]
+    LengthMismatch { expected: usize, actual: usize },
+    #[error("decompressed payload is not UTF-8")]
+    InvalidUtf8,
+    // The payload decompressed to data with another SHA-256 (hex) than the one it was sent with
+    #[error("decompressed payload has SHA-256 {actual}, expected {expected}")]
+    IntegrityFailure { expected: String, actual: String },
+}
+
+fn local_dictionary(found: &Option<String>) -> String {
+    match found {
+        Some(found) => format!("the local dictionary is {}", found),
+        None => "no dictionary is installed".to_string(),
+    }
+}
+
+impl CompressionError {
+    // Producing a payload is part of writing a result; everything else means an input (a memo, a
+    // payload file, an encrypted result) couldn't be read back
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CompressionError::Compress(_) => 4,
+            CompressionError::IntegrityFailure { .. } => 5,
+            _ => 3,
+        }
+    }
+}
+
+pub fn sha256_hex(data: &str) -> String {
+    hex::encode(Sha256::digest(data.as_bytes()))
+}
+
+pub fn zk_compress(data: &str, backend: CompressionBackend, encoding: Encoding) -> Result<CompressedPayload, CompressionError> {
+    let payload = encoding.encode(&compress_bytes(data, backend)?);
+    trace!("Compressed {} bytes to {} with {}: {}", data.len(), payload.len(), backend, payload);
+    Ok(CompressedPayload { payload, sha256: sha256_hex(data) })
+}
+
+// The header and body zk_compress encodes, for stages that work on raw bytes (encryption)
+pub fn compress_bytes(data: &str, backend: CompressionBackend) -> Result<Vec<u8>, CompressionError> {
+    let (id, body) = match active_dictionary(backend) {
+        Some(dictionary) => (ZSTD_DICTIONARY_ID, ZstdWithDictionary(dictionary).compress(data.as_bytes())?),
+        None => (backend.id(), backend.compressor().compress(data.as_bytes())?),
+    };
+    let mut bytes = Vec::with_capacity(HEADER_LEN + body.len());
+    bytes.push(id);
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend(body);
+    Ok(bytes)
+}
+
+// Exact inverse of zk_compress, whichever backend produced the payload. Memo logs may wrap the
+// payload in quotes, which are ignored.
+pub fn zk_decompress(compressed_data: &str, encoding: Encoding) -> Result<String, CompressionError> {
+    trace!("Attempting to decompress: {}", compressed_data);
+    let bytes = encoding.decode(compressed_data.trim().trim_matches('"'))?;
+    decompress_bytes(&bytes)
+}
+
+// zk_decompress for a payload read back from elsewhere (a memo): also checks that it decompressed
+// to the data whose SHA-256 it was sent with
+pub fn zk_decompress_verified(compressed_data: &str, encoding: Encoding, expected_sha256: &str) -> Result<String, CompressionError> {
+    let decompressed = zk_decompress(compressed_data, encoding)?;
+    let actual = sha256_hex(&decompressed);
+    if actual != expected_sha256 {
+        return Err(CompressionError::IntegrityFailure { expected: expected_sha256.to_string(), actual });
+    }
+    Ok(decompressed)
+}
+
+pub fn decompress_bytes(bytes: &[u8]) -> Result<String, CompressionError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(CompressionError::Truncated);
+    }
+    let expected = u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]) as usize;
+    let body = &bytes[HEADER_LEN..];
+
+    let decompressed = match (bytes[0], DICTIONARY.as_ref()) {
+        (ZSTD_DICTIONARY_ID, Some(dictionary)) => ZstdWithDictionary(dictionary).decompress(body)?,
+        (ZSTD_DICTIONARY_ID, None) => {
+            return Err(CompressionError::DictionaryMismatch { expected: short_hash(&body[..body.len().min(32)]), found: None });
+        }
+        (id, _) => CompressionBackend::from_id(id).ok_or(CompressionError::UnknownBackend(id))?.compressor().decompress(body)?,
+    };
+    if decompressed.len() != expected {
+        return Err(CompressionError::LengthMismatch { expected, actual: decompressed.len() });
+    }
+    let decompressed = String::from_utf8(decompressed).map_err(|_| CompressionError::InvalidUtf8)?;
+    trace!("Decompressed data: {}", decompressed);
+    Ok(decompressed)
+}
+
+// SHA-256 of the data a payload was made from: stored as-is in a digest-only payload, recomputed
+// from the decompressed data otherwise
+pub fn payload_sha256(bytes: &[u8]) -> Result<[u8; 32], CompressionError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(CompressionError::Truncated);
+    }
+    if bytes[0] == CompressionBackend::Digest.id() {
+        return <[u8; 32]>::try_from(&bytes[HEADER_LEN..])
+            .map_err(|_| CompressionError::Corrupted("a digest payload holds 32 bytes".to_string()));
+    }
+    Ok(Sha256::digest(decompress_bytes(bytes)?.as_bytes()).into())
+}
+
+// Backs the `train-dict` subcommand: trains a zstd dictionary on the results of a JSON Lines
+// file and installs it in the state directory. Samples are the on-chain payloads, i.e. each
+// envelope without its compression stats and signature.
+pub fn run_train_dict(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config.train_dict_input.as_ref().ok_or_else(|| AnalyzerError::Usage("train-dict requires a file path".to_string()))?;
+    let contents = fs::read_to_string(path).map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e)))?;
+    let mut samples = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let mut envelope: Value = serde_json::from_str(line).map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e)))?;
+        if let Some(fields) = envelope.as_object_mut() {
+            for field in ["compression_stats", "signature", "public_key"] {
+                fields.remove(field);
+            }
+        }
+        samples.push(envelope.to_string().into_bytes());
+    }
+    if samples.len() < MIN_TRAINING_SAMPLES {
+        return Err(AnalyzerError::Usage(format!(
+            "{} holds {} results, training a dictionary needs at least {}",
+            path.display(),
+            samples.len(),
+            MIN_TRAINING_SAMPLES
+        ))
+        .into());
+    }
+
+    let dictionary = ZstdDictionary::new(zstd::dict::from_samples(&samples, DICTIONARY_SIZE)?);
+    fs::create_dir_all(state_dir())?;
+    let dictionary_path = ZstdDictionary::path();
+    let tmp_path = dictionary_path.with_extension("tmp");
+    fs::write(&tmp_path, &dictionary.bytes)
+        .and_then(|()| fs::rename(&tmp_path, &dictionary_path))
+        .map_err(|e| AnalyzerError::OutputWrite(format!("{}: {}", dictionary_path.display(), e)))?;
+    info!("Trained zstd dictionary {} on {} results", dictionary.hash(), samples.len());
+    // Payloads compressed with a previous dictionary can no longer be decompressed
+    println!("{} ({} bytes, dictionary {})", dictionary_path.display(), dictionary.bytes.len(), dictionary.hash());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose, Engine as _};
+    use proptest::prelude::*;
+
+    const REVERSIBLE: [CompressionBackend; 2] = [CompressionBackend::Deflate, CompressionBackend::Zstd];
+
+    proptest! {
+        #[test]
+        fn decompress_inverts_compress(data in any::<String>()) {
+            for backend in REVERSIBLE {
+                for encoding in [Encoding::Base64, Encoding::Base58, Encoding::Hex] {
+                    let compressed = zk_compress(&data, backend, encoding).unwrap();
+                    prop_assert_eq!(zk_decompress(&compressed.payload, encoding), Ok(data.clone()));
+                    prop_assert_eq!(compressed.sha256, sha256_hex(&data));
+                }
+            }
+        }
+
+        // Whatever a payload went through, decoding gives back the data or a typed error
+        #[test]
+        fn base64_decoding_tolerates_alphabet_and_padding(data in any::<String>(), noise in any::<String>(), cut in any::<prop::sample::Index>()) {
+            let compressed = zk_compress(&data, CompressionBackend::Deflate, Encoding::Base64).unwrap().payload;
+            let bytes = Encoding::Base64.decode(&compressed).unwrap();
+            let mut variants = vec![
+                general_purpose::STANDARD.encode(&bytes),
+                general_purpose::URL_SAFE.encode(&bytes),
+                general_purpose::URL_SAFE_NO_PAD.encode(&bytes),
+                format!("  \"{}\"\r\n", compressed),
+            ];
+            // A trailing `+` turned into a space is indistinguishable from surrounding whitespace
+            if !compressed.ends_with('+') {
+                variants.push(compressed.replace('+', " "));
+            }
+            for variant in variants {
+                for encoding in [Encoding::Base64, Encoding::Base64Url] {
+                    prop_assert_eq!(zk_decompress(&variant, encoding), Ok(data.clone()));
+                }
+            }
+
+            let mut corrupted = compressed.clone();
+            corrupted.insert_str(cut.index(compressed.len() + 1), &noise);
+            for encoding in [Encoding::Base64, Encoding::Base64Url, Encoding::Base58, Encoding::Hex] {
+                let _ = zk_decompress(&corrupted, encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn dictionary_payloads_need_their_own_dictionary() {
+        let samples: Vec<String> = (0..200)
+            .map(|i| format!(r#"{{"schema_version":10,"result":{{"most_common_word":"word{}","count":{}}}}}"#, i % 7, i))
+            .collect();
+        let dictionary = ZstdDictionary::new(zstd::dict::from_samples(&samples, 1024).unwrap());
+        let other = ZstdDictionary::new(b"a different dictionary".to_vec());
+
+        let data = br#"{"schema_version":10,"result":{"most_common_word":"word3","count":42}}"#;
+        let body = ZstdWithDictionary(&dictionary).compress(data).unwrap();
+        assert_eq!(ZstdWithDictionary(&dictionary).decompress(&body).unwrap(), data);
+        assert_eq!(
+            ZstdWithDictionary(&other).decompress(&body),
+            Err(CompressionError::DictionaryMismatch { expected: dictionary.hash(), found: Some(other.hash()) }),
+        );
+    }
+
+    #[test]
+    fn digest_only_payloads_are_irreversible() {
+        let compressed = zk_compress("{\"count\":3}", CompressionBackend::Digest, Encoding::Hex).unwrap();
+        assert_eq!(zk_decompress(&compressed.payload, Encoding::Hex), Err(CompressionError::Irreversible));
+        assert!(compressed.payload.ends_with(&compressed.sha256));
+    }
+
+    #[test]
+    fn corrupted_payloads_are_typed_errors() {
+        let data = r#"{"most_common_word":"solana","count":3}"#;
+        for backend in REVERSIBLE {
+            let mut bytes = Encoding::Hex.decode(&zk_compress(data, backend, Encoding::Hex).unwrap().payload).unwrap();
+
+            assert!(matches!(zk_decompress("zz", Encoding::Hex), Err(CompressionError::Encoding(_))));
+            assert_eq!(zk_decompress(&hex::encode(&bytes[..3]), Encoding::Hex), Err(CompressionError::Truncated));
+
+            bytes[1] ^= 1;
+            assert_eq!(
+                zk_decompress(&hex::encode(&bytes), Encoding::Hex),
+                Err(CompressionError::LengthMismatch { expected: data.len() ^ 1, actual: data.len() }),
+            );
+            bytes[1] ^= 1;
+
+            let truncated = hex::encode(&bytes[..bytes.len() - 4]);
+            assert!(matches!(zk_decompress(&truncated, Encoding::Hex), Err(CompressionError::Corrupted(_) | CompressionError::LengthMismatch { .. })));
+
+            bytes[0] = 9;
+            assert_eq!(zk_decompress(&hex::encode(&bytes), Encoding::Hex), Err(CompressionError::UnknownBackend(9)));
+        }
+
+        let mut not_utf8 = vec![CompressionBackend::Deflate.id(), 2, 0, 0, 0];
+        not_utf8.extend(Deflate.compress(&[0xff, 0xfe]).unwrap());
+        assert_eq!(zk_decompress(&hex::encode(&not_utf8), Encoding::Hex), Err(CompressionError::InvalidUtf8));
+
+        let compressed = zk_compress(data, CompressionBackend::Deflate, Encoding::Hex).unwrap();
+        assert_eq!(zk_decompress_verified(&compressed.payload, Encoding::Hex, &compressed.sha256), Ok(data.to_string()));
+        let other = sha256_hex("something else");
+        assert_eq!(
+            zk_decompress_verified(&compressed.payload, Encoding::Hex, &other),
+            Err(CompressionError::IntegrityFailure { expected: other, actual: compressed.sha256 }),
+        );
+    }
+
+    #[test]
+    fn errors_map_to_exit_codes() {
+        let missing = CompressionError::DictionaryMismatch { expected: "5b1e".to_string(), found: None };
+        assert_eq!(missing.to_string(), "payload needs zstd dictionary 5b1e, but no dictionary is installed");
+
+        let errors: [(Box<dyn std::error::Error>, u8); 4] = [
+            (Box::new(CompressionError::Compress("out of memory".to_string())), 4),
+            (Box::new(missing), 3),
+            (Box::new(CompressionError::Truncated), 3),
+            (Box::new(CompressionError::IntegrityFailure { expected: "a".to_string(), actual: "b".to_string() }), 5),
+        ];
+        for (error, code) in errors {
+            assert_eq!(crate::error::exit_code(error.as_ref()), code, "{}", error);
+        }
+    }
+}
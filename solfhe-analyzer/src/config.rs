@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+// On-disk configuration (`--config solfhe.toml`). Every field is optional: unset fields keep the
+// built-in defaults, and command line flags override whatever the file sets.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub interval_secs: Option<u64>,
+    pub batch_size: Option<usize>,
+    pub history_limit: Option<u32>,
+    pub use_visits_table: Option<bool>,
+    pub history_retries: Option<u32>,
+    pub history_retry_delay_ms: Option<u64>,
+    pub history_path: Option<PathBuf>,
+    pub keywords: Option<String>,
+    // name -> regex, see Extractor
+    pub extractors: Option<BTreeMap<String, String>>,
+    pub exclude_domains: Option<String>,
+    pub allow_domains: Option<String>,
+    pub ignored_words: Option<Vec<String>>,
+    pub strip_params: Option<Vec<String>>,
+    pub strip_subdomains: Option<Vec<String>>,
+    pub schemes: Option<Vec<String>>,
+    pub exclude_schemes: Option<Vec<String>>,
+    pub redact_params: Option<Vec<String>>,
+    pub output: Option<PathBuf>,
+    pub report: Option<PathBuf>,
+    pub signing_key: Option<PathBuf>,
+    pub submit: Option<String>,
+    pub keypair: Option<PathBuf>,
+    pub cluster: Option<String>,
+    pub rpc_url: Option<String>,
+    pub commitment: Option<String>,
+    pub rpc_timeout_secs: Option<u64>,
+    pub priority_fee: Option<String>,
+    pub program_id: Option<String>,
+    pub hmac_keys: Option<Vec<String>>,
+    pub report_categories: Option<bool>,
+    pub append: Option<bool>,
+    #[serde(alias = "output_max_size")]
+    pub max_output_size: Option<u64>,
+    pub output_keep: Option<usize>,
+    pub webhook: Option<String>,
+    pub webhook_timeout_secs: Option<u64>,
+    pub share_aggregates: Option<String>,
+    pub format: Option<String>,
+    pub encoding: Option<String>,
+    pub compression: Option<String>,
+    pub hash: Option<String>,
+    pub fhe: Option<String>,
+    pub include_local: Option<bool>,
+    pub case_sensitive: Option<bool>,
+    pub keep_numeric: Option<bool>,
+    pub detail: Option<bool>,
+    pub anonymize: Option<bool>,
+    pub once: Option<bool>,
+    pub backfill: Option<bool>,
+    pub since: Option<String>,
+    pub notify: Option<bool>,
+    pub compression_stats: Option<bool>,
+    pub encrypt: Option<bool>,
+    pub prove: Option<bool>,
+    pub profile_report: Option<bool>,
+    pub passphrase_file: Option<PathBuf>,
+    pub tfidf: Option<bool>,
+    pub session_gap_mins: Option<u64>,
+    pub history: Option<usize>,
+    pub seen_capacity: Option<usize>,
+    pub cooccurrence_top: Option<usize>,
+    pub deltas: Option<bool>,
+    pub timings: Option<bool>,
+    pub domains: Option<bool>,
+    pub max_report_words: Option<usize>,
+    pub max_tracked_words: Option<usize>,
+    pub log_level: Option<String>,
+    pub log_json: Option<bool>,
+}
+
+impl FileConfig {
+    pub fn load(path: &Path) -> Result<FileConfig, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+        let config = toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+        Ok(config)
+    }
+}
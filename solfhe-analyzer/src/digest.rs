@@ -0,0 +1,146 @@
+use std::fmt;
+use std::fs;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::canonical::canonical_json;
+use crate::compression::{payload_sha256, CompressionError};
+use crate::encoding::Encoding;
+use crate::error::AnalyzerError;
+use crate::Config;
+
+// How an envelope is turned into the string that is compressed on-chain, recorded in the
+// envelope's `canonicalization` field. Versions are never changed once shipped, only added, so
+// every published payload stays verifiable:
+//   1: the envelope without the fields added after compression (compression_stats, signature,
+//      public_key, hmac, memo_signature), as serde_json writes a Value: keys sorted, no whitespace, floats in
+//      their shortest round-tripping form. The key order is only as sorted as serde_json's Map,
+//      which another crate enabling serde_json/preserve_order quietly turns into insertion order.
+//   2: the same fields as canonical_json writes them (RFC 8785 style: keys sorted regardless of
+//      how the Value was built, ECMAScript number formatting); signatures and HMAC tags cover
+//      the same form
+pub const CANONICALIZATION_VERSION: u32 = 2;
+
+// Envelopes from before the field existed were written the version 1 way
+pub fn legacy_canonicalization() -> u32 {
+    1
+}
+
+// memo_signature came later; envelopes from before never have it, so dropping it changes none of
+// their payloads
+const POST_COMPRESSION_FIELDS: [&str; 5] = ["compression_stats", "signature", "public_key", "hmac", "memo_signature"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    // The original is not a JSON object
+    InvalidJson(String),
+    // Written by a newer analyzer with a canonicalization this one doesn't know
+    UnsupportedCanonicalization(u64),
+    // The compressed payload can't be decoded or decompressed
+    Compression(CompressionError),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::InvalidJson(detail) => write!(f, "original is not a result envelope: {}", detail),
+            VerifyError::UnsupportedCanonicalization(version) => {
+                write!(f, "canonicalization version {} is newer than this analyzer supports", version)
+            }
+            VerifyError::Compression(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+// The string compressed on-chain for `envelope`, in the canonicalization the envelope names
+pub fn canonical_payload(envelope: &Value) -> Result<String, VerifyError> {
+    let version = envelope.get("canonicalization").map_or(Some(legacy_canonicalization() as u64), Value::as_u64);
+    let without_post_compression_fields = || -> Result<Value, VerifyError> {
+        let mut payload = envelope.clone();
+        let fields = payload.as_object_mut().ok_or_else(|| VerifyError::InvalidJson("not an object".to_string()))?;
+        for field in POST_COMPRESSION_FIELDS {
+            fields.remove(field);
+        }
+        Ok(payload)
+    };
+    match version {
+        Some(1) => Ok(without_post_compression_fields()?.to_string()),
+        Some(2) => Ok(canonical_json(&without_post_compression_fields()?)),
+        Some(version) => Err(VerifyError::UnsupportedCanonicalization(version)),
+        None => Err(VerifyError::InvalidJson("canonicalization is not a number".to_string())),
+    }
+}
+
+// Whether `compressed` (an encoded on-chain payload) was made from the envelope in `original_json`.
+// The digest of the canonical original is compared in constant time with the payload's: its body
+// for a digest-only payload, otherwise the digest of what it decompresses to. Comparing digests
+// rather than recompressing keeps old payloads verifiable when a compressor's output changes.
+pub fn zk_verify(compressed: &str, original_json: &str) -> Result<bool, VerifyError> {
+    let envelope: Value = serde_json::from_str(original_json).map_err(|e| VerifyError::InvalidJson(e.to_string()))?;
+    let encoding: Encoding = serde_json::from_value(envelope["encoding"].clone()).unwrap_or_default();
+    let expected: [u8; 32] = Sha256::digest(canonical_payload(&envelope)?.as_bytes()).into();
+
+    let bytes = encoding.decode(compressed.trim().trim_matches('"')).map_err(VerifyError::Compression)?;
+    let actual = payload_sha256(&bytes).map_err(VerifyError::Compression)?;
+    Ok(bool::from(actual.ct_eq(&expected)))
+}
+
+// Backs `verify COMPRESSED ORIGINAL`: a file holding an on-chain payload and the JSON envelope it
+// should have been made from
+pub fn run_verify_digest(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let (Some(compressed_path), Some(original_path)) = (&config.verify_input, &config.verify_original) else {
+        return Err(AnalyzerError::Usage("verify requires a compressed payload file and a JSON file".to_string()).into());
+    };
+    let read = |path: &std::path::PathBuf| fs::read_to_string(path).map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e)));
+    let (compressed, original) = (read(compressed_path)?, read(original_path)?);
+
+    match zk_verify(&compressed, &original) {
+        Ok(true) => {
+            println!("valid: {} is the compressed form of {}", compressed_path.display(), original_path.display());
+            Ok(())
+        }
+        Ok(false) => Err(AnalyzerError::DigestMismatch(format!("{} was not made from {}", compressed_path.display(), original_path.display())).into()),
+        Err(e) => Err(AnalyzerError::DigestMismatch(e.to_string()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::{zk_compress, CompressionBackend};
+    use serde_json::json;
+
+    #[test]
+    fn payloads_verify_against_their_original_only() {
+        let envelope = json!({
+            "canonicalization": 2,
+            "encoding": "hex",
+            "result": { "most_common_word": "solana", "count": 3, "ratio": 0.1, "share": 1.0 },
+        });
+        // Pretty printed, with the fields added after compression
+        let mut written = envelope.clone();
+        written["signature"] = json!("sig");
+        let written = serde_json::to_string_pretty(&written).unwrap();
+
+        for backend in [CompressionBackend::Digest, CompressionBackend::Deflate, CompressionBackend::Zstd] {
+            let compressed = zk_compress(&canonical_payload(&envelope).unwrap(), backend, Encoding::Hex).unwrap();
+            assert_eq!(zk_verify(&compressed.payload, &written), Ok(true), "{}", backend);
+            assert_eq!(zk_verify(&compressed.payload, &written.replace("solana", "solanA")), Ok(false));
+        }
+
+        // Version 1 payloads stay verifiable
+        let mut legacy = envelope.clone();
+        legacy["canonicalization"] = json!(1);
+        assert!(canonical_payload(&envelope).unwrap().contains(r#""share":1}"#));
+        assert!(canonical_payload(&legacy).unwrap().contains(r#""share":1.0}"#));
+        let compressed = zk_compress(&canonical_payload(&legacy).unwrap(), CompressionBackend::Deflate, Encoding::Hex).unwrap();
+        assert_eq!(zk_verify(&compressed.payload, &legacy.to_string()), Ok(true));
+
+        let future = json!({ "canonicalization": 3, "encoding": "hex" }).to_string();
+        assert_eq!(zk_verify("00", &future), Err(VerifyError::UnsupportedCanonicalization(3)));
+    }
+}
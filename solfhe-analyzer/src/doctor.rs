@@ -0,0 +1,115 @@
+use std::fs::File;
+
+use crate::error::AnalyzerError;
+use crate::native::{check_history_schema, history_sources, open_history, query_recent_urls};
+use crate::Config;
+
+// One line of the doctor's report; a failure carries what to do about it
+struct Check {
+    name: &'static str,
+    outcome: Result<String, (AnalyzerError, &'static str)>,
+}
+
+impl Check {
+    fn print(&self) {
+        match &self.outcome {
+            Ok(detail) => println!("pass  {}: {}", self.name, detail),
+            Err((error, hint)) => {
+                println!("fail  {}: {}", self.name, error);
+                println!("      hint: {}", hint);
+            }
+        }
+    }
+}
+
+// Adds the check and returns whether it passed
+fn push(checks: &mut Vec<Check>, check: Check) -> bool {
+    let passed = check.outcome.is_ok();
+    checks.push(check);
+    passed
+}
+
+// The checks in order, stopping at the first failure since every check needs the one before it
+fn checks(config: &Config) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    // --history-path doesn't need the home directory, the browser locations do
+    if config.history_path.is_none() {
+        let home = match dirs::home_dir() {
+            Some(home) => Ok(home.display().to_string()),
+            None => Err((
+                AnalyzerError::Extraction("the home directory can't be determined".to_string()),
+                "set HOME (USERPROFILE on Windows), or point --history-path at a Chrome History file",
+            )),
+        };
+        if !push(&mut checks, Check { name: "home directory", outcome: home }) {
+            return checks;
+        }
+    }
+
+    let sources = history_sources(config);
+    let Some(source) = sources.iter().find(|source| source.path.exists()) else {
+        let missing = AnalyzerError::NoHistorySource { searched: sources.iter().map(|source| source.path.clone()).collect() };
+        push(&mut checks, Check {
+            name: "history file",
+            outcome: Err((missing, "open Chrome once so it creates its Default profile, or point --history-path at a Chrome History file")),
+        });
+        return checks;
+    };
+    let path = &source.path;
+    push(&mut checks, Check { name: "history file", outcome: Ok(format!("{} ({})", path.display(), source.browser)) });
+
+    let readable = File::open(path).map(|_| "yes".to_string()).map_err(|e| {
+        (
+            AnalyzerError::Extraction(format!("{}: {}", path.display(), e)),
+            "check the file's permissions; on macOS, give the terminal Full Disk Access in System Settings > Privacy & Security",
+        )
+    });
+    if !push(&mut checks, Check { name: "readable", outcome: readable }) {
+        return checks;
+    }
+
+    let conn = open_history(path)
+        .map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e)))
+        .and_then(|conn| check_history_schema(&conn, source.browser).map(|()| conn));
+    let conn = match conn {
+        Ok(conn) => conn,
+        Err(e) => {
+            let hint = match e {
+                AnalyzerError::SchemaMismatch { .. } => "the file isn't a Chrome history database, or Chrome changed its schema; please report the detail above",
+                _ => "the file may be corrupt or locked by a running Chrome; close Chrome and try again",
+            };
+            push(&mut checks, Check { name: "sqlite", outcome: Err((e, hint)) });
+            return checks;
+        }
+    };
+    push(&mut checks, Check { name: "sqlite", outcome: Ok("opened read-only, urls table as expected".to_string()) });
+
+    let urls = match query_recent_urls(&conn, 1) {
+        Ok(urls) if urls.is_empty() => Err((
+            AnalyzerError::Extraction("the history has no URLs".to_string()),
+            "browse a few pages first; Incognito windows are never recorded, and Chrome can take a while to write new visits",
+        )),
+        Ok(_) => Ok("at least one URL can be read".to_string()),
+        Err(e) => Err((
+            AnalyzerError::Extraction(format!("{}: {}", path.display(), e)),
+            "the file may be corrupt or locked by a running Chrome; close Chrome and try again",
+        )),
+    };
+    push(&mut checks, Check { name: "urls", outcome: urls });
+    checks
+}
+
+// Backs `doctor`: why there are no results, one pass/fail line per check with a hint for a
+// failure. Only reads: the history is opened read-only and no state is created. Exits with the
+// code the failure would have given `run` (2 for a missing history file, 3 for an unreadable one).
+pub fn run_doctor(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let mut checks = checks(config);
+    for check in &checks {
+        check.print();
+    }
+    match checks.pop().map(|check| check.outcome) {
+        Some(Err((error, _))) => Err(error.into()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+use std::str::FromStr;
+
+use base64::alphabet;
+use base64::engine::{general_purpose, DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+use crate::compression::CompressionError;
+
+// How the compressed result bytes are rendered as text for the memo, the results database and
+// the envelope's `encoding` field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    #[default]
+    Base64,
+    Base64Url,
+    Base58,
+    Hex,
+}
+
+// Reads base64 with or without padding; decode maps the URL-safe alphabet onto the standard one
+// first, so one engine reads both
+const BASE64_ANY_PADDING: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_encode_padding(false).with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "base64" => Ok(Encoding::Base64),
+            "base64url" => Ok(Encoding::Base64Url),
+            "base58" => Ok(Encoding::Base58),
+            "hex" => Ok(Encoding::Hex),
+            other => Err(format!("Unknown encoding: {} (expected base64, base64url, base58 or hex)", other)),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::Base64 => "base64",
+            Encoding::Base64Url => "base64url",
+            Encoding::Base58 => "base58",
+            Encoding::Hex => "hex",
+        })
+    }
+}
+
+impl Encoding {
+    // Both base64 variants are unpadded
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Base64 => general_purpose::STANDARD_NO_PAD.encode(bytes),
+            Encoding::Base64Url => general_purpose::URL_SAFE_NO_PAD.encode(bytes),
+            Encoding::Base58 => bs58::encode(bytes).into_string(),
+            Encoding::Hex => hex::encode(bytes),
+        }
+    }
+
+    // Lenient about how the text got here: surrounding whitespace and line breaks are dropped, and
+    // either base64 variant reads both alphabets, padded or not. A space inside base64 is taken for
+    // a `+` that a query string decoded.
+    pub fn decode(&self, text: &str) -> Result<Vec<u8>, CompressionError> {
+        let text = text.trim();
+        let invalid = |e: &dyn fmt::Display| CompressionError::Encoding(format!("{}: {}", self, e));
+        Ok(match self {
+            Encoding::Base64 | Encoding::Base64Url => {
+                let standard: String = text.chars()
+                    .filter(|c| !matches!(c, '\r' | '\n'))
+                    .map(|c| match c {
+                        ' ' | '-' => '+',
+                        '_' => '/',
+                        c => c,
+                    })
+                    .collect();
+                BASE64_ANY_PADDING.decode(standard).map_err(|e| invalid(&e))?
+            }
+            Encoding::Base58 => bs58::decode(text).into_vec().map_err(|e| invalid(&e))?,
+            Encoding::Hex => hex::decode(text).map_err(|e| invalid(&e))?,
+        })
+    }
+}
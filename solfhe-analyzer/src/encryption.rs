@@ -0,0 +1,473 @@
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::compression::{compress_bytes, decompress_bytes, CompressionBackend};
+use crate::encoding::Encoding;
+use crate::error::AnalyzerError;
+use crate::signing::canonical_bytes;
+use crate::state::state_dir;
+use crate::Config;
+
+pub const KEY_ENV: &str = "SOLFHE_ENCRYPTION_KEY";
+pub const PASSPHRASE_ENV: &str = "SOLFHE_PASSPHRASE";
+pub const NEW_PASSPHRASE_ENV: &str = "SOLFHE_NEW_PASSPHRASE";
+
+// Bumped whenever the sealed layout changes
+const SEALED_VERSION: u8 = 1;
+const RAW_KEY: u8 = 0;
+// PBKDF2 keys with a per-process salt; no longer written, but earlier results still decrypt
+const PBKDF2_KEY: u8 = 1;
+const ARGON2_KEY: u8 = 2;
+const KDF_SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+// OWASP's 2023 recommendation for PBKDF2-HMAC-SHA256
+const PBKDF2_ROUNDS: u32 = 600_000;
+// OWASP's 2023 recommendation for Argon2id: 19 MiB of memory, 2 passes, 1 lane
+const ARGON2_M_COST: u32 = 19 * 1024;
+const ARGON2_T_COST: u32 = 2;
+const ARGON2_P_COST: u32 = 1;
+// The costs of a sealed result come from the result itself, so a hostile one could ask for
+// gigabytes of memory or minutes of hashing before its tag is even checked. Four times the
+// defaults leaves room to raise them.
+const ARGON2_MAX_M_COST: u32 = 4 * ARGON2_M_COST;
+const ARGON2_MAX_T_COST: u32 = 4 * ARGON2_T_COST;
+const ARGON2_MAX_P_COST: u32 = 4 * ARGON2_P_COST;
+// Salt followed by m_cost, t_cost and p_cost as u32 LE
+const ARGON2_HEADER_LEN: usize = KDF_SALT_LEN + 12;
+
+// Fields that are added to an envelope after sealing, or are the sealed result itself, and so are
+// not part of the associated data
+const UNAUTHENTICATED_FIELDS: [&str; 3] = ["encrypted_result", "compression_stats", "memo_signature"];
+
+// Argon2id salt and cost parameters for passphrase keys. They are kept in the state directory
+// (kdf.json) so the same passphrase reproduces the same key on this machine until `rekey`
+// replaces them, and they travel with every sealed result, so decrypting only needs the passphrase.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    salt: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    // sha256 of the derived key, so a mistyped passphrase is refused before anything is sealed with it
+    #[serde(default)]
+    check: String,
+}
+
+impl KdfParams {
+    fn path() -> PathBuf {
+        state_dir().join("kdf.json")
+    }
+
+    fn load() -> Result<Option<KdfParams>, AnalyzerError> {
+        let path = KdfParams::path();
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(AnalyzerError::Usage(format!("{}: {}", path.display(), e))),
+        };
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|e| AnalyzerError::Usage(format!("{}: {}", path.display(), e)))
+    }
+
+    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(state_dir())?;
+        fs::write(KdfParams::path(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Fresh salt with the current default costs, checked against `passphrase`
+    fn create(passphrase: &str) -> Result<(KdfParams, [u8; 32]), AnalyzerError> {
+        let mut params = KdfParams {
+            salt: hex::encode(rand::random::<[u8; KDF_SALT_LEN]>()),
+            m_cost: ARGON2_M_COST,
+            t_cost: ARGON2_T_COST,
+            p_cost: ARGON2_P_COST,
+            check: String::new(),
+        };
+        let key = params.derive(passphrase).map_err(AnalyzerError::Usage)?;
+        params.check = key_check(&key);
+        Ok((params, key))
+    }
+
+    fn unlock(&self, passphrase: &str) -> Result<[u8; 32], AnalyzerError> {
+        let key = self.derive(passphrase).map_err(AnalyzerError::Usage)?;
+        if key_check(&key) != self.check {
+            return Err(AnalyzerError::Usage(format!(
+                "the passphrase does not match the one set up in {} (use `rekey` to change it)",
+                KdfParams::path().display()
+            )));
+        }
+        Ok(key)
+    }
+
+    fn derive(&self, passphrase: &str) -> Result<[u8; 32], String> {
+        let salt = hex::decode(&self.salt).map_err(|e| format!("invalid KDF salt: {}", e))?;
+        derive_argon2_key(passphrase, &salt, self.m_cost, self.t_cost, self.p_cost)
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut header = hex::decode(&self.salt).expect("KDF salt was checked when the key was derived");
+        for cost in [self.m_cost, self.t_cost, self.p_cost] {
+            header.extend_from_slice(&cost.to_le_bytes());
+        }
+        header
+    }
+}
+
+fn key_check(key: &[u8; 32]) -> String {
+    hex::encode(Sha256::digest(key))
+}
+
+fn derive_argon2_key(passphrase: &str, salt: &[u8], m_cost: u32, t_cost: u32, p_cost: u32) -> Result<[u8; 32], String> {
+    if m_cost > ARGON2_MAX_M_COST || t_cost > ARGON2_MAX_T_COST || p_cost > ARGON2_MAX_P_COST {
+        return Err(format!(
+            "Argon2 costs m={} t={} p={} exceed the maximum m={} t={} p={}",
+            m_cost, t_cost, p_cost, ARGON2_MAX_M_COST, ARGON2_MAX_T_COST, ARGON2_MAX_P_COST
+        ));
+    }
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32)).map_err(|e| format!("invalid Argon2 parameters: {}", e))?;
+    let mut key = [0; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("deriving the key: {}", e))?;
+    Ok(key)
+}
+
+fn derive_pbkdf2_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS)
+}
+
+// --passphrase-file, then the environment variable, then an interactive prompt with echo
+// disabled when stdin is a terminal
+fn read_passphrase(file: Option<&Path>, env: &str, prompt: &str, confirm: bool) -> Result<String, AnalyzerError> {
+    let passphrase = if let Some(path) = file {
+        fs::read_to_string(path)
+            .map_err(|e| AnalyzerError::Usage(format!("{}: {}", path.display(), e)))?
+            .trim_end_matches(['\r', '\n'])
+            .to_string()
+    } else if let Ok(passphrase) = std::env::var(env) {
+        passphrase
+    } else if std::io::stdin().is_terminal() {
+        let prompted = |prompt: &str| rpassword::prompt_password(prompt).map_err(|e| AnalyzerError::Usage(format!("reading the passphrase: {}", e)));
+        let passphrase = prompted(prompt)?;
+        if confirm && prompted("Repeat the passphrase: ")? != passphrase {
+            return Err(AnalyzerError::Usage("the passphrases do not match".to_string()));
+        }
+        passphrase
+    } else {
+        return Err(AnalyzerError::Usage(format!(
+            "encryption needs a key in {}, a passphrase in {} or --passphrase-file, or a terminal to prompt on",
+            KEY_ENV, env
+        )));
+    };
+    if passphrase.is_empty() {
+        return Err(AnalyzerError::Usage("the passphrase is empty".to_string()));
+    }
+    Ok(passphrase)
+}
+
+// A key from SOLFHE_ENCRYPTION_KEY (64 hex characters), or derived from a passphrase with the
+// machine's KdfParams
+pub struct EncryptionKey {
+    key: [u8; 32],
+    kdf: Option<KdfParams>,
+}
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+impl EncryptionKey {
+    pub fn resolve(passphrase_file: Option<&Path>) -> Result<EncryptionKey, AnalyzerError> {
+        // The first passphrase sets up kdf.json, so a prompt asks for it twice
+        let stored = KdfParams::load()?;
+        Ok(match KeySource::resolve(passphrase_file, stored.is_none())? {
+            KeySource::Raw(key) => EncryptionKey { key, kdf: None },
+            KeySource::Passphrase(passphrase) => match stored {
+                Some(params) => EncryptionKey { key: params.unlock(&passphrase)?, kdf: Some(params) },
+                None => {
+                    let (params, key) = KdfParams::create(&passphrase)?;
+                    params.save().map_err(|e| AnalyzerError::Usage(format!("{}: {}", KdfParams::path().display(), e)))?;
+                    EncryptionKey { key, kdf: Some(params) }
+                }
+            },
+        })
+    }
+
+    // `version || key kind || [kdf salt and costs] || nonce || AES-256-GCM(plaintext)`. The nonce is drawn
+    // here for every message and there is no way to pass one in, so a nonce can't be reused.
+    fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.key));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: plaintext, aad })
+            .expect("AES-GCM encryption of an in-memory buffer cannot fail");
+
+        let mut sealed = vec![SEALED_VERSION];
+        match &self.kdf {
+            Some(params) => {
+                sealed.push(ARGON2_KEY);
+                sealed.extend(params.header());
+            }
+            None => sealed.push(RAW_KEY),
+        }
+        sealed.extend_from_slice(&nonce);
+        sealed.extend(ciphertext);
+        sealed
+    }
+}
+
+// How decrypt obtains the key for a sealed result, which may name its own KDF salt
+pub enum KeySource {
+    Raw([u8; 32]),
+    Passphrase(String),
+}
+
+impl KeySource {
+    // An explicit --passphrase-file wins over SOLFHE_ENCRYPTION_KEY
+    pub fn resolve(passphrase_file: Option<&Path>, confirm: bool) -> Result<KeySource, AnalyzerError> {
+        if passphrase_file.is_none() {
+            if let Ok(hex_key) = std::env::var(KEY_ENV) {
+                return hex::decode(hex_key.trim())
+                    .ok()
+                    .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                    .map(KeySource::Raw)
+                    .ok_or_else(|| AnalyzerError::Usage(format!("{} must be 64 hex characters (32 bytes)", KEY_ENV)));
+            }
+        }
+        read_passphrase(passphrase_file, PASSPHRASE_ENV, "Passphrase: ", confirm).map(KeySource::Passphrase)
+    }
+
+    fn decrypt(&self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+        let (&version, rest) = sealed.split_first().ok_or("empty ciphertext")?;
+        if version != SEALED_VERSION {
+            return Err(format!("unsupported encryption version {}", version));
+        }
+        let (&kind, rest) = rest.split_first().ok_or("truncated ciphertext")?;
+        let (key, rest) = match (kind, self) {
+            (RAW_KEY, KeySource::Raw(key)) => (*key, rest),
+            (PBKDF2_KEY, KeySource::Passphrase(passphrase)) if rest.len() >= KDF_SALT_LEN => {
+                let (salt, rest) = rest.split_at(KDF_SALT_LEN);
+                (derive_pbkdf2_key(passphrase, salt), rest)
+            }
+            (ARGON2_KEY, KeySource::Passphrase(passphrase)) if rest.len() >= ARGON2_HEADER_LEN => {
+                let (header, rest) = rest.split_at(ARGON2_HEADER_LEN);
+                let (salt, costs) = header.split_at(KDF_SALT_LEN);
+                let cost = |index: usize| u32::from_le_bytes(costs[index * 4..index * 4 + 4].try_into().unwrap());
+                (derive_argon2_key(passphrase, salt, cost(0), cost(1), cost(2))?, rest)
+            }
+            (RAW_KEY, _) => return Err(format!("sealed with a raw key, set {}", KEY_ENV)),
+            (PBKDF2_KEY | ARGON2_KEY, KeySource::Raw(_)) => {
+                return Err(format!("sealed with a passphrase, set {} or pass --passphrase-file", PASSPHRASE_ENV))
+            }
+            _ => return Err("truncated or unknown key header".to_string()),
+        };
+        if rest.len() < NONCE_LEN {
+            return Err("truncated ciphertext".to_string());
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| "authentication failed: wrong key or tampered result".to_string())
+    }
+}
+
+// Everything in the envelope besides the result itself is authenticated, so metadata like
+// generated_at or schema_version can't be swapped between sealed results
+fn associated_data(envelope: &Value) -> Vec<u8> {
+    let mut metadata = envelope.clone();
+    if let Some(fields) = metadata.as_object_mut() {
+        fields.remove("result");
+        for field in UNAUTHENTICATED_FIELDS {
+            fields.remove(field);
+        }
+    }
+    canonical_bytes(&metadata)
+}
+
+fn envelope_encoding(envelope: &Value) -> Encoding {
+    serde_json::from_value(envelope["encoding"].clone()).unwrap_or_default()
+}
+
+// Replaces `result` with `encrypted_result`: the result JSON compressed with the envelope's
+// backend, then encrypted
+pub fn seal(envelope: &mut Value, key: &EncryptionKey) -> Result<(), Box<dyn std::error::Error>> {
+    let compression: CompressionBackend = serde_json::from_value(envelope["compression"].clone()).unwrap_or_default();
+    if !compression.is_reversible() {
+        return Err("encryption needs a reversible --compression (deflate or zstd)".into());
+    }
+    let Some(fields) = envelope.as_object_mut() else {
+        return Ok(());
+    };
+    let Some(result) = fields.remove("result") else {
+        return Ok(());
+    };
+    let compressed = compress_bytes(&result.to_string(), compression)?;
+    let sealed = key.encrypt(&compressed, &associated_data(envelope));
+    let encoded = envelope_encoding(envelope).encode(&sealed);
+    envelope["encrypted_result"] = Value::String(encoded);
+    Ok(())
+}
+
+// Inverse of seal; envelopes without an encrypted result are returned as they are
+pub fn open(envelope: &Value, key: &KeySource) -> Result<Value, String> {
+    let Some(encoded) = envelope["encrypted_result"].as_str() else {
+        return Ok(envelope.clone());
+    };
+    let sealed = envelope_encoding(envelope).decode(encoded).map_err(|e| format!("invalid encrypted_result: {}", e))?;
+    let compressed = key.decrypt(&sealed, &associated_data(envelope))?;
+    let result = decompress_bytes(&compressed).map_err(|e| e.to_string())?;
+
+    let mut opened = envelope.clone();
+    if let Some(fields) = opened.as_object_mut() {
+        fields.remove("encrypted_result");
+        fields.insert("result".to_string(), serde_json::from_str(&result).map_err(|e| e.to_string())?);
+    }
+    Ok(opened)
+}
+
+// Backs the `decrypt` subcommand: prints every result of a JSON or JSON Lines file with its
+// encrypted result restored
+pub fn run_decrypt(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config.decrypt_input.as_ref().ok_or_else(|| AnalyzerError::Usage("decrypt requires a file path".to_string()))?;
+    let key = KeySource::resolve(config.passphrase_file.as_deref(), false)?;
+    let contents = fs::read_to_string(path).map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e)))?;
+    let documents: Vec<Value> = match serde_json::from_str(&contents) {
+        Ok(document) => vec![document],
+        Err(_) => contents.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e)))?,
+    };
+
+    for (index, document) in documents.iter().enumerate() {
+        let opened = open(document, &key).map_err(|e| AnalyzerError::Decryption(format!("result {}: {}", index + 1, e)))?;
+        println!("{}", serde_json::to_string_pretty(&opened)?);
+    }
+    Ok(())
+}
+
+// Backs the `rekey` subcommand: after checking the current passphrase (if one is set up), stores
+// a fresh salt for the new passphrase. Sealed results carry their own KDF parameters, so earlier
+// results keep decrypting with the passphrase they were sealed with.
+pub fn run_rekey(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(current) = KdfParams::load()? {
+        let passphrase = read_passphrase(config.passphrase_file.as_deref(), PASSPHRASE_ENV, "Current passphrase: ", false)?;
+        current.unlock(&passphrase)?;
+    }
+    let passphrase = read_passphrase(config.new_passphrase_file.as_deref(), NEW_PASSPHRASE_ENV, "New passphrase: ", true)?;
+    let (params, _) = KdfParams::create(&passphrase)?;
+    params.save()?;
+    println!("Stored new key derivation parameters in {}", KdfParams::path().display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn envelope() -> Value {
+        json!({
+            "schema_version": 11,
+            "generated_at": "2024-05-01T12:00:00Z",
+            "encoding": "base64",
+            "compression": "deflate",
+            "result": { "most_common_word": "solana", "count": 3, "top_words": [{ "word": "solana", "count": 3 }] }
+        })
+    }
+
+    #[test]
+    fn sealed_results_open_only_untampered_with_the_right_key() {
+        let key = EncryptionKey { key: [7; 32], kdf: None };
+        let mut sealed = envelope();
+        seal(&mut sealed, &key).unwrap();
+        assert!(sealed.get("result").is_none());
+        assert_eq!(open(&sealed, &KeySource::Raw([7; 32])), Ok(envelope()));
+        assert!(open(&sealed, &KeySource::Raw([8; 32])).is_err());
+
+        // One flipped ciphertext bit
+        let mut bytes = Encoding::Base64.decode(sealed["encrypted_result"].as_str().unwrap()).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        let mut flipped = sealed.clone();
+        flipped["encrypted_result"] = json!(Encoding::Base64.encode(&bytes));
+        assert_eq!(open(&flipped, &KeySource::Raw([7; 32])), Err("authentication failed: wrong key or tampered result".to_string()));
+
+        // Metadata is covered by the associated data
+        let mut backdated = sealed;
+        backdated["generated_at"] = json!("2020-01-01T00:00:00Z");
+        assert!(open(&backdated, &KeySource::Raw([7; 32])).is_err());
+    }
+
+    #[test]
+    fn passphrase_results_carry_their_kdf_parameters() {
+        let (params, key) = KdfParams::create("correct horse").unwrap();
+        assert_eq!(params.unlock("correct horse").unwrap(), key);
+        assert!(params.unlock("battery staple").is_err());
+
+        let mut sealed = envelope();
+        seal(&mut sealed, &EncryptionKey { key, kdf: Some(params) }).unwrap();
+        assert_eq!(open(&sealed, &KeySource::Passphrase("correct horse".to_string())), Ok(envelope()));
+        assert!(open(&sealed, &KeySource::Passphrase("battery staple".to_string())).is_err());
+    }
+
+    #[test]
+    fn oversized_argon2_costs_are_refused_before_deriving() {
+        let (params, key) = KdfParams::create("correct horse").unwrap();
+        let mut sealed = envelope();
+        seal(&mut sealed, &EncryptionKey { key, kdf: Some(params) }).unwrap();
+
+        // version, key kind and salt come before m_cost
+        let mut bytes = Encoding::Base64.decode(sealed["encrypted_result"].as_str().unwrap()).unwrap();
+        let m_cost = 2 + KDF_SALT_LEN;
+        bytes[m_cost..m_cost + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        sealed["encrypted_result"] = json!(Encoding::Base64.encode(&bytes));
+        let refused = open(&sealed, &KeySource::Passphrase("correct horse".to_string())).unwrap_err();
+        assert!(refused.contains("exceed the maximum"), "{}", refused);
+    }
+
+    // Known answers pin the KDF: if the defaults or the Argon2 implementation drift, passphrases
+    // stop reproducing the keys of earlier results
+    #[test]
+    fn kdf_parameters_match_known_answers() {
+        assert_eq!((ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST), (19_456, 2, 1));
+        let (params, _) = KdfParams::create("correct horse").unwrap();
+        assert_eq!((params.m_cost, params.t_cost, params.p_cost), (ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST));
+        assert_eq!(params.header().len(), ARGON2_HEADER_LEN);
+
+        let key = derive_argon2_key("correct horse battery staple", b"solfhe-kat-salt!", ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST).unwrap();
+        assert_eq!(hex::encode(key), "8c4fb690b59cab27bc9563a247f026b9f4c27fe3b4a1ffd83cceb139c51cf23c");
+
+        // RFC 9106, section 5.3 (Argon2id with a secret and associated data)
+        let params = argon2::ParamsBuilder::new()
+            .m_cost(32)
+            .t_cost(3)
+            .p_cost(4)
+            .output_len(32)
+            .data(argon2::AssociatedData::new(&[4; 12]).unwrap())
+            .build()
+            .unwrap();
+        let mut tag = [0; 32];
+        Argon2::new_with_secret(&[3; 8], Algorithm::Argon2id, Version::V0x13, params)
+            .unwrap()
+            .hash_password_into(&[1; 32], &[2; 16], &mut tag)
+            .unwrap();
+        assert_eq!(hex::encode(tag), "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659");
+
+        let pbkdf2_key = derive_pbkdf2_key("correct horse battery staple", b"solfhe-kat-salt!");
+        assert_eq!(hex::encode(pbkdf2_key), "14da75edba09be6d7f0bb22272be968275f7880f066ea7c83334199d737a9331");
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::compression::CompressionError;
+
+// Failures callers are expected to tell apart; everything else stays a boxed error. Each kind
+// maps to a documented process exit code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnalyzerError {
+    // None of the known browser history databases exist
+    NoHistorySource { searched: Vec<PathBuf> },
+    // The history database opened fine but lacks the `urls` table or columns the analyzer reads,
+    // e.g. after the browser bumped its schema
+    SchemaMismatch { browser: String, detail: String },
+    // A history database or input file exists but could not be read
+    Extraction(String),
+    // A result could not be written to its destination
+    OutputWrite(String),
+    // A result's signature is missing or doesn't match its content or the expected signer
+    InvalidSignature(String),
+    // A compressed payload was not made from the result it was checked against
+    DigestMismatch(String),
+    // An encrypted result could not be opened: wrong key, or tampered with
+    Decryption(String),
+    // A top-keyword or inclusion proof is missing, malformed or doesn't verify
+    InvalidProof(String),
+    // Invalid command line or config file
+    Usage(String),
+}
+
+impl AnalyzerError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            AnalyzerError::NoHistorySource { .. } => 2,
+            AnalyzerError::SchemaMismatch { .. } | AnalyzerError::Extraction(_) => 3,
+            AnalyzerError::OutputWrite(_) => 4,
+            AnalyzerError::InvalidSignature(_) | AnalyzerError::DigestMismatch(_) => 5,
+            AnalyzerError::Decryption(_) => 6,
+            AnalyzerError::InvalidProof(_) => 7,
+            AnalyzerError::Usage(_) => 64,
+        }
+    }
+}
+
+// Typed errors map to their own code; anything else is a generic failure
+pub fn exit_code(error: &(dyn std::error::Error + 'static)) -> u8 {
+    if let Some(error) = error.downcast_ref::<CompressionError>() {
+        return error.exit_code();
+    }
+    error.downcast_ref::<AnalyzerError>().map_or(1, AnalyzerError::exit_code)
+}
+
+impl fmt::Display for AnalyzerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnalyzerError::NoHistorySource { searched } => {
+                let searched: Vec<String> = searched.iter().map(|path| path.display().to_string()).collect();
+                write!(f, "no browser history found (looked for {})", searched.join(", "))
+            }
+            AnalyzerError::SchemaMismatch { browser, detail } => {
+                write!(f, "{} history database has an unsupported schema: {}", browser, detail)
+            }
+            AnalyzerError::Extraction(detail) => write!(f, "failed to read history: {}", detail),
+            AnalyzerError::OutputWrite(detail) => write!(f, "failed to write output: {}", detail),
+            AnalyzerError::InvalidSignature(detail) => write!(f, "signature verification failed: {}", detail),
+            AnalyzerError::DigestMismatch(detail) => write!(f, "digest verification failed: {}", detail),
+            AnalyzerError::Decryption(detail) => write!(f, "decryption failed: {}", detail),
+            AnalyzerError::InvalidProof(detail) => write!(f, "proof verification failed: {}", detail),
+            AnalyzerError::Usage(detail) => write!(f, "{}", detail),
+        }
+    }
+}
+
+impl std::error::Error for AnalyzerError {}
@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+
+use base64::{engine::general_purpose, Engine as _};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tfhe::prelude::*;
+use tfhe::{generate_keys, set_server_key, ClientKey, ConfigBuilder, FheUint32, PublicKey, ServerKey};
+use tracing::info;
+
+use crate::state::state_dir;
+
+// Experimental (--fhe): keyword counters are TFHE ciphertexts and every increment is a
+// homomorphic addition, so the counts only exist in the clear where the client key is.
+//   decrypt: the analyzer holds the client key and decrypts each batch's totals when it is emitted
+//   emit:    the counters are emitted as ciphertexts for an evaluator holding the client key;
+//            only the public and server keys are needed here
+// The keywords themselves stay readable, only their counts are encrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FheMode {
+    Decrypt,
+    Emit,
+}
+
+impl std::str::FromStr for FheMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "decrypt" => Ok(FheMode::Decrypt),
+            "emit" => Ok(FheMode::Emit),
+            other => Err(format!("Unknown FHE mode: {} (expected decrypt or emit)", other)),
+        }
+    }
+}
+
+fn key_path(name: &str) -> PathBuf {
+    state_dir().join(format!("fhe-{}-key.bin", name))
+}
+
+fn read_key<T: DeserializeOwned>(name: &str) -> Result<Option<T>, Box<dyn std::error::Error>> {
+    match fs::read(key_path(name)) {
+        Ok(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", key_path(name).display(), e).into()),
+    }
+}
+
+fn write_key<T: Serialize>(name: &str, key: &T) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(key_path(name), bincode::serialize(key)?)?;
+    Ok(())
+}
+
+pub struct FheKeys {
+    // None in emit mode once the client key file has been moved to the evaluator
+    client: Option<ClientKey>,
+    public: PublicKey,
+    server: ServerKey,
+}
+
+impl FheKeys {
+    pub fn generate() -> FheKeys {
+        let (client, server) = generate_keys(ConfigBuilder::default().build());
+        let public = PublicKey::new(&client);
+        FheKeys { client: Some(client), public, server }
+    }
+
+    // Generated on first use (this takes a while and the server key is large) as fhe-client-key.bin,
+    // fhe-public-key.bin and fhe-server-key.bin in the state directory
+    pub fn load_or_generate(mode: FheMode) -> Result<FheKeys, Box<dyn std::error::Error>> {
+        if let (Some(public), Some(server)) = (read_key("public")?, read_key("server")?) {
+            let client = read_key("client")?;
+            if client.is_none() && mode == FheMode::Decrypt {
+                return Err(format!("--fhe decrypt needs the client key {}", key_path("client").display()).into());
+            }
+            return Ok(FheKeys { client, public, server });
+        }
+
+        info!("Generating TFHE keys");
+        let keys = FheKeys::generate();
+        fs::create_dir_all(state_dir())?;
+        write_key("client", keys.client.as_ref().expect("generated keys include the client key"))?;
+        write_key("public", &keys.public)?;
+        write_key("server", &keys.server)?;
+        Ok(keys)
+    }
+}
+
+pub struct EncryptedCounters {
+    keys: FheKeys,
+    counters: HashMap<String, FheUint32>,
+}
+
+impl EncryptedCounters {
+    pub fn new(keys: FheKeys) -> EncryptedCounters {
+        EncryptedCounters { keys, counters: HashMap::new() }
+    }
+
+    // A new word starts from an encryption of zero under the public key; the counts are then
+    // added to it homomorphically
+    pub fn add(&mut self, counts: &HashMap<String, u32>) {
+        // The server key is per thread, and the runtime may move the analyzer between threads
+        set_server_key(self.keys.server.clone());
+        for (word, &count) in counts {
+            let public = &self.keys.public;
+            let counter = self.counters.entry(word.clone()).or_insert_with(|| FheUint32::encrypt(0u32, public));
+            *counter += count;
+        }
+    }
+
+    // The batch's totals in the clear; the counters start over
+    pub fn take_decrypted(&mut self) -> Result<HashMap<String, u32>, String> {
+        let client = self.keys.client.as_ref().ok_or("decrypting FHE counters needs the client key")?;
+        Ok(self.counters.drain().map(|(word, counter)| (word, counter.decrypt(client))).collect())
+    }
+
+    // The batch's counters as base64 bincode ciphertexts, for an evaluator holding the client key;
+    // the counters start over
+    pub fn take_serialized(&mut self) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+        self.counters.drain()
+            .map(|(word, counter)| Ok((word, general_purpose::STANDARD.encode(bincode::serialize(&counter)?))))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn homomorphic_sums_equal_plaintext_sums() {
+        let batch: [&[(&str, u32)]; 3] = [&[("solana", 2), ("swap", 1)], &[("solana", 3)], &[("swap", 4), ("staking", 1)]];
+        let mut counters = EncryptedCounters::new(FheKeys::generate());
+        let mut plain: HashMap<String, u32> = HashMap::new();
+        for link in batch {
+            let counts: HashMap<String, u32> = link.iter().map(|&(word, count)| (word.to_string(), count)).collect();
+            for (word, &count) in &counts {
+                *plain.entry(word.clone()).or_insert(0) += count;
+            }
+            counters.add(&counts);
+        }
+        assert_eq!(counters.take_decrypted(), Ok(plain));
+
+        counters.add(&HashMap::from([("solana".to_string(), 7)]));
+        let serialized = counters.take_serialized().unwrap();
+        let ciphertext: FheUint32 = bincode::deserialize(&general_purpose::STANDARD.decode(&serialized["solana"]).unwrap()).unwrap();
+        let solana: u32 = ciphertext.decrypt(counters.keys.client.as_ref().unwrap());
+        assert_eq!(solana, 7);
+        assert!(counters.counters.is_empty());
+    }
+}
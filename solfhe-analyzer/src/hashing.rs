@@ -0,0 +1,133 @@
+use std::fmt;
+use std::str::FromStr;
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::poseidon::PoseidonSponge;
+use ark_crypto_primitives::sponge::CryptographicSponge;
+use ark_ff::{BigInteger, PrimeField};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::proof::ARKWORKS_POSEIDON;
+
+// Bytes packed into one field element; 31 bytes always stay below the BN254 scalar modulus
+pub const CHUNK_BYTES: usize = 31;
+
+// The hash behind url_root (and the inclusion proofs against it) and the payload commitment in
+// compression_stats. Selected with --hash and recorded in the envelope's `hash` field. SHA-256 is
+// cheap everywhere but costs tens of thousands of constraints per block inside a SNARK; Poseidon
+// over the BN254 scalar field, the instance the top-keyword proof already commits with, costs a
+// few hundred.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashFunction {
+    #[default]
+    Sha256,
+    Poseidon,
+}
+
+impl FromStr for HashFunction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(HashFunction::Sha256),
+            "poseidon" => Ok(HashFunction::Poseidon),
+            other => Err(format!("Unknown hash: {} (expected sha256 or poseidon)", other)),
+        }
+    }
+}
+
+impl fmt::Display for HashFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            HashFunction::Sha256 => "sha256",
+            HashFunction::Poseidon => "poseidon",
+        })
+    }
+}
+
+impl HashFunction {
+    // 32 bytes either way; a Poseidon digest is the field element, little-endian
+    pub fn digest(&self, bytes: &[u8]) -> [u8; 32] {
+        match self {
+            HashFunction::Sha256 => Sha256::digest(bytes).into(),
+            HashFunction::Poseidon => field_bytes(&arkworks_poseidon(&bytes_to_fields(bytes))),
+        }
+    }
+}
+
+// How bytes (a keyword's UTF-8, a URL hash, a payload) become field elements: their length,
+// then little-endian chunks of up to CHUNK_BYTES. The length keeps `ab` and `ab\0` apart.
+pub fn bytes_to_fields(bytes: &[u8]) -> Vec<Fr> {
+    let mut fields = vec![Fr::from(bytes.len() as u64)];
+    fields.extend(bytes.chunks(CHUNK_BYTES).map(Fr::from_le_bytes_mod_order));
+    fields
+}
+
+// The sponge over ARKWORKS_POSEIDON, which is not circomlib-compatible (see there)
+pub fn arkworks_poseidon(inputs: &[Fr]) -> Fr {
+    let mut sponge = PoseidonSponge::new(&ARKWORKS_POSEIDON);
+    sponge.absorb(&inputs.to_vec());
+    sponge.squeeze_field_elements::<Fr>(1)[0]
+}
+
+pub fn field_bytes(value: &Fr) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    bytes.copy_from_slice(&value.into_bigint().to_bytes_le());
+    bytes
+}
+
+// Inverse of field_bytes for digests read back from hex; anything at or above the modulus wraps
+pub fn bytes_field(bytes: &[u8; 32]) -> Fr {
+    Fr::from_le_bytes_mod_order(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_crypto_primitives::sponge::constraints::CryptographicSpongeVar;
+    use ark_crypto_primitives::sponge::poseidon::constraints::PoseidonSpongeVar;
+    use ark_r1cs_std::alloc::AllocVar;
+    use ark_r1cs_std::fields::fp::FpVar;
+    use ark_r1cs_std::R1CSVar;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    #[test]
+    fn bytes_pack_into_length_prefixed_chunks() {
+        assert_eq!(bytes_to_fields(b"solana"), [Fr::from(6u64), Fr::from_le_bytes_mod_order(b"solana")]);
+        assert_eq!(bytes_to_fields(&[7; 31]).len(), 2);
+        assert_eq!(bytes_to_fields(&[7; 32]).len(), 3);
+        assert_ne!(HashFunction::Poseidon.digest(b"ab"), HashFunction::Poseidon.digest(b"ab\0"));
+        let digest = HashFunction::Poseidon.digest(b"solana");
+        assert_eq!(field_bytes(&bytes_field(&digest)), digest);
+    }
+
+    // Pins the instance: a change of constants or sponge in arkworks would change every digest
+    // already recorded with --hash poseidon
+    #[test]
+    fn arkworks_poseidon_known_answers() {
+        let cases: [(&[u8], &str); 3] = [
+            (b"", "859d56a9694dfae7be24a99147e20c90c0aedf7966f487dbdd911d3fa145a513"),
+            (b"solana", "f190eed523b35e8a3863e70ef5e1bae7891564bd3d59f11b179522cc9eafa20c"),
+            (&[0xff; 100], "f5366ca69497c1f13c9732bede1a9d2bb13b7d942881c77ebcdcb345ef58b213"),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(hex::encode(HashFunction::Poseidon.digest(bytes)), expected);
+        }
+    }
+
+    // Circuits recompute these digests with the R1CS sponge, so the native one must agree with it
+    #[test]
+    fn native_poseidon_matches_the_circuit() {
+        let cases: [&[u8]; 3] = [b"", b"solana", &[0xff; 100]];
+        for bytes in cases {
+            let inputs = bytes_to_fields(bytes);
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let vars: Vec<FpVar<Fr>> = inputs.iter().map(|&input| FpVar::new_witness(cs.clone(), || Ok(input)).unwrap()).collect();
+            let mut sponge = PoseidonSpongeVar::new(cs.clone(), &ARKWORKS_POSEIDON);
+            sponge.absorb(&vars).unwrap();
+            assert_eq!(sponge.squeeze_field_elements(1).unwrap()[0].value().unwrap(), arkworks_poseidon(&inputs));
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::Path;
+
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::error::AnalyzerError;
+use crate::signing::{canonical_bytes, read_documents};
+use crate::Config;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// A symmetric alternative to signatures for setups where every machine shares one secret, like a
+// home server collecting results from several laptops. The tag is HMAC-SHA256 over the same
+// canonical bytes a signature covers, so a result can carry both.
+fn mac(key: &str) -> HmacSha256 {
+    HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length")
+}
+
+pub fn tag(envelope: &mut Value, key: &str) {
+    let mut mac = mac(key);
+    mac.update(&canonical_bytes(envelope));
+    let tag = hex::encode(mac.finalize().into_bytes());
+    if let Some(fields) = envelope.as_object_mut() {
+        fields.insert("hmac".to_string(), Value::String(tag));
+    }
+}
+
+// Tries every accepted key, so a verifier can hold the old and the new secret while machines are
+// moved over; returns the index of the key that matched. Comparisons are constant time.
+pub fn verify(envelope: &Value, keys: &[String]) -> Result<usize, String> {
+    let tag = envelope["hmac"].as_str().ok_or("no hmac field")?;
+    let tag = hex::decode(tag).map_err(|e| format!("invalid hmac: {}", e))?;
+    let bytes = canonical_bytes(envelope);
+    keys.iter()
+        .position(|key| {
+            let mut mac = mac(key);
+            mac.update(&bytes);
+            mac.verify_slice(&tag).is_ok()
+        })
+        .ok_or_else(|| "hmac does not match the content under any accepted key".to_string())
+}
+
+// One secret per line; blank lines and `#` comments are skipped
+pub fn read_keys(path: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read HMAC keys {}: {}", path.display(), e))?;
+    Ok(contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+// Backs `verify FILE --hmac`: checks the tag of every result against the configured keys
+pub fn run_verify_hmac(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config.verify_input.as_ref().ok_or_else(|| AnalyzerError::Usage("verify requires a file path".to_string()))?;
+    if config.hmac_keys.is_empty() {
+        return Err(AnalyzerError::Usage("verify --hmac requires hmac_keys in the config file or --hmac-key-file".to_string()).into());
+    }
+
+    let mut failures = Vec::new();
+    for (index, document) in read_documents(path)?.iter().enumerate() {
+        match verify(document, &config.hmac_keys) {
+            Ok(key) => println!("result {}: valid hmac with key {}", index + 1, key + 1),
+            Err(e) => failures.push(format!("result {}: {}", index + 1, e)),
+        }
+    }
+    if failures.is_empty() {
+        return Ok(());
+    }
+    Err(AnalyzerError::InvalidSignature(failures.join("; ")).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn rotated_keys_verify_and_tampering_is_rejected() {
+        let mut envelope = json!({ "schema_version": 13, "result": { "most_common_word": "solana", "count": 3 } });
+        tag(&mut envelope, "old secret");
+
+        let written: Value = serde_json::from_str(&serde_json::to_string_pretty(&envelope).unwrap()).unwrap();
+        let keys = ["new secret".to_string(), "old secret".to_string()];
+        assert_eq!(verify(&written, &keys), Ok(1));
+        assert!(verify(&written, &keys[..1]).is_err());
+
+        let mut tampered = written;
+        tampered["result"]["count"] = json!(4);
+        assert!(verify(&tampered, &keys).is_err());
+    }
+}
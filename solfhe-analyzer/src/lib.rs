@@ -0,0 +1,78 @@
+// 🏗️ Developed by: Baturalp Güvenç 
+
+/* Gerekli kütüphaneleri kullanıyoruz: rusqlite (SQLite işlemleri için), url (URL ayrıştırma için), serde_json (JSON işlemleri için) ve Rust standart kütüphanesinden çeşitli modüller.
+HistoryAnalyzer adında bir struct tanımlıyoruz. Bu struct, linkleri ve kelime sayımlarını tutar.
+chrome_history_candidates fonksiyonu, farklı işletim sistemleri için Chrome geçmiş dosyasının olası konumlarını belirler.
+extract_links metodu, Chrome'un geçmiş veritabanından son URL'leri çeker (varsayılan 5, --history-limit ile ayarlanır).
+analyze_link metodu, her bir linki ayrıştırır ve içindeki anlamlı kelimeleri (özellikle blockchain ağı isimlerini) sayar.
+get_most_common_word ve to_json metotları, en sık kullanılan kelimeyi bulur ve JSON formatında çıktı üretir.
+run metodu, sürekli çalışan bir döngü içinde her 60 saniyede bir yeni linkleri kontrol eder. */
+
+pub mod analysis;
+#[cfg(feature = "native")]
+mod binary;
+#[cfg(feature = "native")]
+mod canonical;
+#[cfg(feature = "native")]
+mod cli;
+#[cfg(feature = "native")]
+mod compression;
+#[cfg(feature = "native")]
+mod config;
+#[cfg(feature = "native")]
+mod digest;
+#[cfg(feature = "native")]
+mod doctor;
+#[cfg(feature = "native")]
+mod encoding;
+#[cfg(feature = "native")]
+mod encryption;
+#[cfg(feature = "native")]
+mod error;
+#[cfg(feature = "fhe")]
+mod fhe;
+#[cfg(feature = "native")]
+mod hashing;
+#[cfg(feature = "native")]
+mod integrity;
+#[cfg(feature = "native")]
+mod merge;
+#[cfg(feature = "native")]
+mod merkle;
+#[cfg(feature = "native")]
+mod onchain;
+#[cfg(feature = "native")]
+mod output;
+#[cfg(feature = "native")]
+mod pretty;
+#[cfg(feature = "native")]
+mod proof;
+#[cfg(feature = "native")]
+mod registry;
+#[cfg(feature = "native")]
+mod report;
+#[cfg(feature = "native")]
+mod signing;
+#[cfg(feature = "native")]
+mod result;
+#[cfg(feature = "native")]
+mod results_db;
+#[cfg(feature = "native")]
+mod state;
+#[cfg(feature = "native")]
+mod submit;
+#[cfg(feature = "native")]
+mod summary;
+#[cfg(feature = "native")]
+mod telemetry;
+#[cfg(feature = "native")]
+mod native;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(feature = "native")]
+pub use native::*;
+#[cfg(feature = "native")]
+use native::{hash_url, unix_now};
+#[cfg(feature = "wasm")]
+pub use wasm::analyze_urls;
@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::AnalyzerError;
+use crate::output::OutputFormat;
+use crate::Config;
+
+// Combined interest profile from results written on several machines
+#[derive(Debug, Serialize)]
+pub struct MergedCounts {
+    pub most_common_word: Option<String>,
+    pub count: u32,
+    pub sources: usize,
+    pub word_counts: BTreeMap<String, u32>,
+}
+
+// Keyword counts of one emitted result (an envelope or a bare result). Older results without
+// `word_counts` fall back to their top words.
+fn result_counts(value: &Value) -> HashMap<String, u32> {
+    let result = value.get("result").unwrap_or(value);
+    let count = |v: &Value| v.as_u64().unwrap_or(0) as u32;
+    match result["word_counts"].as_object() {
+        Some(word_counts) if !word_counts.is_empty() => {
+            word_counts.iter().map(|(word, n)| (word.clone(), count(n))).collect()
+        }
+        _ => result["top_words"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| Some((entry["word"].as_str()?.to_string(), count(&entry["count"]))))
+            .collect(),
+    }
+}
+
+// Accepts a JSON result file, a JSON Lines / NDJSON stream of results, or the CSV export.
+// Every batch in a file is added up, since each batch only counts its own links.
+fn read_counts(path: &Path) -> Result<HashMap<String, u32>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut counts = HashMap::new();
+
+    if path.extension().is_some_and(|ext| ext == "csv") {
+        let mut reader = csv::Reader::from_reader(contents.as_bytes());
+        for record in reader.records() {
+            let record = record?;
+            let keyword = record.get(1).ok_or_else(|| format!("{}: row without a keyword column", path.display()))?;
+            let count: u32 = record.get(2).unwrap_or_default().parse()?;
+            *counts.entry(keyword.to_string()).or_insert(0) += count;
+        }
+        return Ok(counts);
+    }
+
+    let values: Vec<Value> = match serde_json::from_str(&contents) {
+        Ok(value) => vec![value],
+        Err(_) => contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("{} is neither a JSON result nor JSON Lines: {}", path.display(), e))?,
+    };
+    for value in &values {
+        merge_counts(&mut counts, result_counts(value));
+    }
+    Ok(counts)
+}
+
+// Plain addition; keys only present on one machine (e.g. a different network list) are kept as is
+fn merge_counts(total: &mut HashMap<String, u32>, counts: HashMap<String, u32>) {
+    for (word, count) in counts {
+        *total.entry(word).or_insert(0) += count;
+    }
+}
+
+pub fn run_merge(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    if config.merge_inputs.is_empty() {
+        return Err(AnalyzerError::Usage("merge requires at least one result file".to_string()).into());
+    }
+    if matches!(config.file_format(), OutputFormat::Csv | OutputFormat::Pretty | OutputFormat::MessagePack | OutputFormat::Cbor) {
+        return Err(AnalyzerError::Usage("merge output supports json, jsonl, ndjson or toml".to_string()).into());
+    }
+
+    let mut total = HashMap::new();
+    for path in &config.merge_inputs {
+        let counts = read_counts(path).map_err(|e| AnalyzerError::Extraction(e.to_string()))?;
+        merge_counts(&mut total, counts);
+    }
+
+    // Highest count wins; ties go to the alphabetically first word so the output is reproducible
+    let top = total.iter().max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)));
+    let merged = MergedCounts {
+        most_common_word: top.map(|(word, _)| word.clone()),
+        count: top.map_or(0, |(_, &count)| count),
+        sources: config.merge_inputs.len(),
+        word_counts: total.into_iter().collect(),
+    };
+    println!("{}", config.file_format().render(&serde_json::to_value(merged)?)?);
+    Ok(())
+}
@@ -0,0 +1,273 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::AnalyzerError;
+use crate::hashing::{arkworks_poseidon, bytes_field, bytes_to_fields, field_bytes, HashFunction};
+use crate::results_db::ResultsDb;
+use crate::{hash_url, Config};
+
+// Merkle tree over the salted URL hashes of one batch (see hash_url), so raw URLs never appear in
+// a tree or a proof. Leaves are sorted and deduplicated, which is what makes non-inclusion
+// provable: an absent hash falls between two adjacent leaves. Nodes are domain separated
+// (RFC 6962 style) so a leaf can't pose as an inner node:
+//   leaf  = sha256(0x00 || url hash)
+//   inner = sha256(0x01 || left || right)
+// With --hash poseidon the prefix is the first field element absorbed instead, and nodes are
+// field elements (see hashing.rs):
+//   leaf  = poseidon(0, bytes_to_fields(url hash))
+//   inner = poseidon(1, left, right)
+// An odd node at the end of a level is carried up unchanged. A single-leaf tree's root is that
+// leaf's node; an empty batch has the hash of nothing (empty_root) and no proofs of inclusion.
+const LEAF_PREFIX: u8 = 0x00;
+const INNER_PREFIX: u8 = 0x01;
+
+type Node = [u8; 32];
+
+pub fn empty_root(hash: HashFunction) -> String {
+    hex::encode(hash.digest(&[]))
+}
+
+fn leaf_node(url_hash: &str, hash: HashFunction) -> Result<Node, String> {
+    let bytes = hex::decode(url_hash).map_err(|e| format!("invalid URL hash {}: {}", url_hash, e))?;
+    Ok(match hash {
+        HashFunction::Sha256 => Sha256::new().chain_update([LEAF_PREFIX]).chain_update(bytes).finalize().into(),
+        HashFunction::Poseidon => {
+            let mut inputs = bytes_to_fields(&bytes);
+            inputs.insert(0, LEAF_PREFIX.into());
+            field_bytes(&arkworks_poseidon(&inputs))
+        }
+    })
+}
+
+fn inner_node(left: &Node, right: &Node, hash: HashFunction) -> Node {
+    match hash {
+        HashFunction::Sha256 => Sha256::new().chain_update([INNER_PREFIX]).chain_update(left).chain_update(right).finalize().into(),
+        HashFunction::Poseidon => field_bytes(&arkworks_poseidon(&[INNER_PREFIX.into(), bytes_field(left), bytes_field(right)])),
+    }
+}
+
+fn next_level(level: &[Node], hash: HashFunction) -> Vec<Node> {
+    level.chunks(2)
+        .map(|pair| match pair {
+            [left, right] => inner_node(left, right, hash),
+            [odd] => *odd,
+            _ => unreachable!("chunks(2) yields one or two nodes"),
+        })
+        .collect()
+}
+
+// The sorted, deduplicated leaves of a batch
+pub fn leaves(url_hashes: &[String]) -> Vec<String> {
+    let mut leaves = url_hashes.to_vec();
+    leaves.sort();
+    leaves.dedup();
+    leaves
+}
+
+pub fn root(url_hashes: &[String], hash: HashFunction) -> Result<String, String> {
+    let mut level = leaves(url_hashes).iter().map(|leaf| leaf_node(leaf, hash)).collect::<Result<Vec<_>, _>>()?;
+    if level.is_empty() {
+        return Ok(empty_root(hash));
+    }
+    while level.len() > 1 {
+        level = next_level(&level, hash);
+    }
+    Ok(hex::encode(level[0]))
+}
+
+// One leaf and the sibling hashes from it up to the root. Which side each sibling is on follows
+// from `index` and the proof's leaf_count, so the position can't be swapped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafPath {
+    pub index: usize,
+    pub leaf: String,
+    pub siblings: Vec<String>,
+}
+
+impl LeafPath {
+    fn new(leaves: &[String], index: usize, hash: HashFunction) -> Result<LeafPath, String> {
+        let mut level = leaves.iter().map(|leaf| leaf_node(leaf, hash)).collect::<Result<Vec<_>, _>>()?;
+        let mut position = index;
+        let mut siblings = Vec::new();
+        while level.len() > 1 {
+            if position % 2 == 1 {
+                siblings.push(hex::encode(level[position - 1]));
+            } else if position + 1 < level.len() {
+                siblings.push(hex::encode(level[position + 1]));
+            }
+            level = next_level(&level, hash);
+            position /= 2;
+        }
+        Ok(LeafPath { index, leaf: leaves[index].clone(), siblings })
+    }
+
+    fn root(&self, leaf_count: usize, hash: HashFunction) -> Result<String, String> {
+        if self.index >= leaf_count {
+            return Err(format!("leaf index {} is outside a tree of {} leaves", self.index, leaf_count));
+        }
+        let mut node = leaf_node(&self.leaf, hash)?;
+        let mut siblings = self.siblings.iter().map(|sibling| {
+            hex::decode(sibling)
+                .ok()
+                .and_then(|bytes| Node::try_from(bytes).ok())
+                .ok_or_else(|| format!("invalid sibling hash {}", sibling))
+        });
+        let mut next_sibling = || siblings.next().unwrap_or_else(|| Err("too few sibling hashes".to_string()));
+        let (mut position, mut count) = (self.index, leaf_count);
+        while count > 1 {
+            if position % 2 == 1 {
+                node = inner_node(&next_sibling()?, &node, hash);
+            } else if position + 1 < count {
+                node = inner_node(&node, &next_sibling()?, hash);
+            }
+            position /= 2;
+            count = count.div_ceil(2);
+        }
+        if siblings.next().is_some() {
+            return Err("too many sibling hashes".to_string());
+        }
+        Ok(hex::encode(node))
+    }
+}
+
+// What `prove-inclusion` prints and `verify-inclusion` checks. For an included URL `leaves` holds
+// its own leaf; otherwise the adjacent leaves that bracket `url_hash` (only one at either end of
+// the tree, none for an empty batch).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub batch_id: i64,
+    // The batch's hash function; proofs from before it was recorded are sha256
+    #[serde(default)]
+    pub hash: HashFunction,
+    pub root: String,
+    pub leaf_count: usize,
+    pub url_hash: String,
+    pub included: bool,
+    pub leaves: Vec<LeafPath>,
+}
+
+impl InclusionProof {
+    pub fn new(batch_id: i64, url_hashes: &[String], url_hash: &str, hash: HashFunction) -> Result<InclusionProof, String> {
+        let leaves = leaves(url_hashes);
+        let (included, indices) = match leaves.binary_search_by(|leaf| leaf.as_str().cmp(url_hash)) {
+            Ok(index) => (true, vec![index]),
+            Err(index) => (false, index.checked_sub(1).into_iter().chain((index < leaves.len()).then_some(index)).collect()),
+        };
+        Ok(InclusionProof {
+            batch_id,
+            hash,
+            root: root(&leaves, hash)?,
+            leaf_count: leaves.len(),
+            url_hash: url_hash.to_string(),
+            included,
+            leaves: indices.into_iter().map(|index| LeafPath::new(&leaves, index, hash)).collect::<Result<_, _>>()?,
+        })
+    }
+
+    // Checks the proof on its own; the caller compares `root` with the batch's recorded root
+    pub fn verify(&self) -> Result<(), String> {
+        for path in &self.leaves {
+            if path.root(self.leaf_count, self.hash)? != self.root {
+                return Err(format!("the path of leaf {} does not lead to the root", path.index));
+            }
+        }
+        let hash = self.url_hash.as_str();
+        match (self.included, self.leaves.as_slice()) {
+            (true, [path]) if path.leaf == hash => Ok(()),
+            (false, []) if self.leaf_count == 0 && self.root == empty_root(self.hash) => Ok(()),
+            (false, [lower, upper]) if lower.index + 1 == upper.index && lower.leaf.as_str() < hash && hash < upper.leaf.as_str() => Ok(()),
+            (false, [upper]) if upper.index == 0 && hash < upper.leaf.as_str() => Ok(()),
+            (false, [lower]) if lower.index + 1 == self.leaf_count && lower.leaf.as_str() < hash => Ok(()),
+            (true, _) => Err("the proof does not contain the URL's leaf".to_string()),
+            (false, _) => Err("the leaves do not bracket the URL's hash".to_string()),
+        }
+    }
+}
+
+// Backs `prove-inclusion URL [--batch ID]`: proves that the URL was, or wasn't, among the links of
+// a stored batch. Without --batch, the latest batch containing the URL is used, or failing that
+// the latest batch.
+pub fn run_prove_inclusion(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let url = config.inclusion_url.as_deref().ok_or_else(|| AnalyzerError::Usage("prove-inclusion requires a URL".to_string()))?;
+    let url_hash = hash_url(url, config);
+    let db = ResultsDb::open_default()?;
+    let batch_id = match config.inclusion_batch {
+        Some(batch_id) => batch_id,
+        None => db.latest_batch_with_url(&url_hash)?
+            .or(db.recent(1)?.first().map(|batch| batch.id))
+            .ok_or_else(|| AnalyzerError::Extraction("the results database holds no batches".to_string()))?,
+    };
+    let url_hashes = db.batch_url_hashes(batch_id)?
+        .ok_or_else(|| AnalyzerError::Extraction(format!("no batch {} with a URL commitment in the results database", batch_id)))?;
+    let hash = db.batch_hash(batch_id)?;
+    let proof = InclusionProof::new(batch_id, &url_hashes, &url_hash, hash).map_err(AnalyzerError::Extraction)?;
+    println!("{}", serde_json::to_string_pretty(&proof)?);
+    Ok(())
+}
+
+// Backs `verify-inclusion FILE`: checks a proof and that its root is the one recorded for the batch
+pub fn run_verify_inclusion(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config.verify_inclusion_input.as_ref().ok_or_else(|| AnalyzerError::Usage("verify-inclusion requires a file path".to_string()))?;
+    let contents = fs::read_to_string(path).map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e)))?;
+    let proof: InclusionProof = serde_json::from_str(&contents).map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e)))?;
+
+    let recorded = ResultsDb::open_default()?.batch_url_root(proof.batch_id)?;
+    if recorded.as_deref() != Some(proof.root.as_str()) {
+        return Err(AnalyzerError::InvalidProof(format!("the root does not match batch {} in the results database", proof.batch_id)).into());
+    }
+    proof.verify().map_err(AnalyzerError::InvalidProof)?;
+    let verdict = if proof.included { "was" } else { "was not" };
+    println!("valid proof: URL hash {} {} in batch {}", proof.url_hash, verdict, proof.batch_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(n: u8) -> String {
+        hex::encode([n; 32])
+    }
+
+    #[test]
+    fn every_leaf_proves_inclusion_and_every_gap_non_inclusion() {
+        for function in [HashFunction::Sha256, HashFunction::Poseidon] {
+            for size in 0..=7u8 {
+                let batch: Vec<String> = (1..=size).map(|n| hash(n * 2)).collect();
+                for n in 0..=size * 2 + 1 {
+                    let proof = InclusionProof::new(1, &batch, &hash(n), function).unwrap();
+                    assert_eq!(proof.root, root(&batch, function).unwrap());
+                    assert_eq!(proof.included, n > 0 && n % 2 == 0 && n <= size * 2);
+                    assert_eq!(proof.verify(), Ok(()), "{} size {} hash {}", function, size, n);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn edge_cases_and_forgeries() {
+        // sha256 of nothing
+        assert_eq!(root(&[], HashFunction::Sha256).unwrap(), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        let single = vec![hash(1)];
+        assert_eq!(root(&single, HashFunction::Sha256).unwrap(), hex::encode(leaf_node(&hash(1), HashFunction::Sha256).unwrap()));
+
+        let batch = vec![hash(2), hash(4), hash(6)];
+        let mut proof = InclusionProof::new(1, &batch, &hash(4), HashFunction::Sha256).unwrap();
+        proof.url_hash = hash(3);
+        assert!(proof.verify().is_err());
+
+        // Skipping a leaf between the bracketing neighbours
+        let mut gap = InclusionProof::new(1, &batch, &hash(3), HashFunction::Sha256).unwrap();
+        gap.leaves[1] = LeafPath::new(&batch, 2, HashFunction::Sha256).unwrap();
+        gap.url_hash = hash(5);
+        assert!(gap.verify().is_err());
+
+        // A proof only verifies under the hash it was made with
+        let mut switched = InclusionProof::new(1, &batch, &hash(4), HashFunction::Poseidon).unwrap();
+        assert_ne!(switched.root, root(&batch, HashFunction::Sha256).unwrap());
+        switched.hash = HashFunction::Sha256;
+        assert!(switched.verify().is_err());
+    }
+}
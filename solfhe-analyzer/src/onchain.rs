@@ -0,0 +1,162 @@
+use std::str::FromStr;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::digest::canonical_payload;
+use crate::error::AnalyzerError;
+use crate::native::current_thread_runtime;
+use crate::registry::decode_result;
+use crate::results_db::{BatchAnchor, ResultsDb};
+use crate::submit::memo_digest;
+use crate::Config;
+
+// Where a stored batch stands against the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Anchoring {
+    // Every recorded memo and result account holds the recomputed digest
+    Anchored,
+    // Not submitted, or not visible at the selected commitment yet
+    Pending(String),
+    Mismatched(String),
+    // Nothing to recompute the digest from, or recorded on another cluster
+    Unverifiable(String),
+}
+
+// The memo of the transaction, None while the transaction isn't visible at the client's
+// commitment. The Memo program logs it as `Memo (len N): "<memo>"`.
+async fn read_memo(client: &RpcClient, signature: &Signature) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    match client.get_signature_status_with_commitment_and_history(signature, client.commitment(), true).await? {
+        None => return Ok(None),
+        Some(Err(e)) => return Err(format!("transaction {} failed: {}", signature, e).into()),
+        Some(Ok(())) => {}
+    }
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Json),
+        commitment: Some(client.commitment()),
+        max_supported_transaction_version: Some(0),
+    };
+    let transaction = client.get_transaction_with_config(signature, config).await?;
+    let Some(OptionSerializer::Some(logs)) = transaction.transaction.meta.map(|meta| meta.log_messages) else {
+        return Err(format!("transaction {} has no logs", signature).into());
+    };
+    let memo = logs
+        .iter()
+        .filter(|log| log.starts_with("Program log: Memo"))
+        .find_map(|log| log.split_once("): ").map(|(_, memo)| memo.trim_matches('"').to_string()));
+    memo.map(Some).ok_or_else(|| format!("transaction {} has no memo", signature).into())
+}
+
+async fn check(client: &RpcClient, config: &Config, batch: &BatchAnchor) -> Result<Anchoring, Box<dyn std::error::Error>> {
+    let cluster = config.cluster_name();
+    if let Some(recorded) = batch.cluster.as_ref().filter(|&recorded| *recorded != cluster) {
+        return Ok(Anchoring::Unverifiable(format!("recorded on {}, not {}", recorded, cluster)));
+    }
+    let Some(payload) = &batch.canonical_payload else {
+        return Ok(Anchoring::Unverifiable("recorded before canonical payloads were stored".to_string()));
+    };
+    // Canonicalized again, in the version the envelope names, rather than hashed as stored
+    let envelope: Value = serde_json::from_str(payload)?;
+    let local = hex::encode(Sha256::digest(canonical_payload(&envelope)?.as_bytes()));
+
+    let mut pending = Vec::new();
+    let mut mismatches = Vec::new();
+    match &batch.memo_signature {
+        Some(signature) => match read_memo(client, &Signature::from_str(signature)?).await? {
+            None => pending.push(format!("memo transaction {} not found", signature)),
+            Some(memo) => match memo_digest(&memo) {
+                Some(onchain) if onchain == local => {}
+                Some(onchain) => mismatches.push(format!("local digest {}, memo digest {} (transaction {})", local, onchain, signature)),
+                None => mismatches.push(format!("memo `{}` is not a result digest (transaction {})", memo, signature)),
+            },
+        },
+        None if batch.result_pda.is_none() => pending.push("not submitted".to_string()),
+        None => {}
+    }
+    if let Some(address) = &batch.result_pda {
+        let address = Pubkey::from_str(address)?;
+        match client.get_account_with_commitment(&address, client.commitment()).await?.value {
+            None => pending.push(format!("result account {} not found", address)),
+            Some(account) => match decode_result(&address, &account.data) {
+                Some(stored) if stored.digest == local => {}
+                Some(stored) => mismatches.push(format!(
+                    "local digest {}, result account {} holds {} (transaction {})",
+                    local,
+                    address,
+                    stored.digest,
+                    batch.memo_signature.as_deref().unwrap_or("unknown")
+                )),
+                None => mismatches.push(format!("{} is not a result account", address)),
+            },
+        }
+    }
+
+    Ok(if !mismatches.is_empty() {
+        Anchoring::Mismatched(mismatches.join("; "))
+    } else if !pending.is_empty() {
+        Anchoring::Pending(pending.join("; "))
+    } else {
+        Anchoring::Anchored
+    })
+}
+
+// Backs `verify-onchain`: reads the memo and result account recorded for each batch back from the
+// chain and compares their digest with one recomputed from the stored canonical payload. Every
+// batch, --batch ID alone, or --batch through --to-batch. Any mismatch fails with exit code 5.
+pub fn run_verify_onchain(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let db = ResultsDb::open_default()?;
+    let (first, last) = match (config.inclusion_batch, config.last_batch) {
+        (Some(batch), None) => (Some(batch), Some(batch)),
+        range => range,
+    };
+    let batches = db.anchors(first, last)?;
+    if batches.is_empty() {
+        return Err(match (first, last) {
+            (Some(first), Some(last)) if first == last => format!("No batch with id {}", first),
+            _ => "No batches to verify".to_string(),
+        }
+        .into());
+    }
+
+    let client = config.rpc_client();
+    let runtime = current_thread_runtime()?;
+    let (mut anchored, mut pending, mut mismatched, mut unverifiable) = (0, 0, 0, 0);
+    for batch in &batches {
+        match runtime.block_on(check(&client, config, batch))? {
+            Anchoring::Anchored => {
+                anchored += 1;
+                println!("{}\tanchored", batch.id);
+            }
+            Anchoring::Pending(detail) => {
+                pending += 1;
+                println!("{}\tpending\t{}", batch.id, detail);
+            }
+            Anchoring::Mismatched(detail) => {
+                mismatched += 1;
+                println!("{}\tmismatch\t{}", batch.id, detail);
+            }
+            Anchoring::Unverifiable(detail) => {
+                unverifiable += 1;
+                println!("{}\tunverifiable\t{}", batch.id, detail);
+            }
+        }
+    }
+    println!(
+        "{} batches: {} anchored, {} pending, {} mismatched, {} unverifiable",
+        batches.len(),
+        anchored,
+        pending,
+        mismatched,
+        unverifiable
+    );
+    if mismatched > 0 {
+        return Err(AnalyzerError::DigestMismatch(format!("{} of {} batches don't match their on-chain digest", mismatched, batches.len())).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,438 @@
+use std::cell::{Cell, RefCell};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{binary, pretty};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    Ndjson,
+    Toml,
+    Csv,
+    Pretty,
+    MessagePack,
+    Cbor,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            // JSON Lines and NDJSON are the same thing: one compact object per line
+            "ndjson" | "jsonl" => Ok(OutputFormat::Ndjson),
+            "toml" => Ok(OutputFormat::Toml),
+            "csv" => Ok(OutputFormat::Csv),
+            "pretty" => Ok(OutputFormat::Pretty),
+            "msgpack" => Ok(OutputFormat::MessagePack),
+            "cbor" => Ok(OutputFormat::Cbor),
+            other => Err(format!(
+                "Unknown output format: {} (expected json, jsonl, ndjson, toml, csv, pretty, msgpack or cbor)",
+                other
+            )),
+        }
+    }
+}
+
+impl OutputFormat {
+    pub fn render(&self, result: &Value) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(match self {
+            OutputFormat::Json => serde_json::to_string_pretty(result)?,
+            OutputFormat::Ndjson => serde_json::to_string(result)?,
+            OutputFormat::Toml => toml::to_string_pretty(&strip_nulls(result))?,
+            OutputFormat::Csv => csv_rows(result, true)?,
+            OutputFormat::Pretty => pretty::render(result, None, false),
+            OutputFormat::MessagePack | OutputFormat::Cbor => {
+                return Err(format!("{:?} output is binary and has no text rendering", self).into())
+            }
+        })
+    }
+
+    pub fn is_binary(&self) -> bool {
+        matches!(self, OutputFormat::MessagePack | OutputFormat::Cbor)
+    }
+
+    // The bytes written to stdout or a file: the text rendering, or for binary formats the
+    // envelope together with its raw payload digest
+    pub fn encode(&self, result: &Value) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        if self.is_binary() {
+            binary::encode(*self, result)
+        } else {
+            Ok(self.render(result)?.into_bytes())
+        }
+    }
+}
+
+const CSV_HEADER: [&str; 5] = ["timestamp", "keyword", "count", "category", "source"];
+
+// Chrome is the only history source so far
+const CSV_SOURCE: &str = "chrome";
+
+// One row per top word, in the result's (count-descending) order. Unlike the other formats the
+// rendered text already ends with a newline.
+pub fn csv_rows(result: &Value, header: bool) -> Result<String, Box<dyn std::error::Error>> {
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
+    if header {
+        writer.write_record(CSV_HEADER)?;
+    }
+    // Rows come from the analysis inside the result envelope
+    let result = &result["result"];
+    let timestamp = result["timestamp"].as_i64().map(|t| t.to_string()).unwrap_or_default();
+    for entry in result["top_words"].as_array().into_iter().flatten() {
+        writer.write_record([
+            timestamp.as_str(),
+            entry["word"].as_str().unwrap_or_default(),
+            &entry["count"].to_string(),
+            entry["category"].as_str().unwrap_or("word"),
+            CSV_SOURCE,
+        ])?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+// TOML has no null, so absent values are dropped rather than failing serialization
+fn strip_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), strip_nulls(v)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().filter(|v| !v.is_null()).map(strip_nulls).collect()),
+        other => other.clone(),
+    }
+}
+
+// A destination for analysis results. The main loop broadcasts every result to all
+// configured sinks concurrently; a failing sink is reported and never blocks the others.
+// Sinks are only ever driven from the analyzer's own task, so they don't need to be Send.
+#[async_trait(?Send)]
+pub trait OutputSink {
+    fn name(&self) -> String;
+    async fn emit(&self, result: &Value) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+// Results are the only thing written to stdout; all diagnostics go to stderr so the stream can be
+// piped straight into jq or a log shipper
+pub struct StdoutSink {
+    format: OutputFormat,
+    out: RefCell<Box<dyn Write>>,
+    csv_header_written: Cell<bool>,
+    color: bool,
+    // Start of the next pretty table's batch window
+    last_timestamp: Cell<Option<i64>>,
+}
+
+impl StdoutSink {
+    pub fn new(format: OutputFormat) -> Self {
+        let mut sink = StdoutSink::with_writer(format, Box::new(io::stdout()));
+        sink.color = pretty::colors_enabled();
+        sink
+    }
+
+    fn with_writer(format: OutputFormat, out: Box<dyn Write>) -> Self {
+        StdoutSink {
+            format,
+            out: RefCell::new(out),
+            csv_header_written: Cell::new(false),
+            color: false,
+            last_timestamp: Cell::new(None),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl OutputSink for StdoutSink {
+    fn name(&self) -> String {
+        "stdout".to_string()
+    }
+
+    async fn emit(&self, result: &Value) -> Result<(), Box<dyn std::error::Error>> {
+        let mut out = self.out.borrow_mut();
+        if self.format == OutputFormat::Csv {
+            // A single header line at the top of the stream, then rows for every batch
+            write!(out, "{}", csv_rows(result, !self.csv_header_written.replace(true))?)?;
+        } else if self.format == OutputFormat::Pretty {
+            let window_start = self.last_timestamp.replace(result["result"]["timestamp"].as_i64());
+            writeln!(out, "{}\n", pretty::render(result, window_start, self.color))?;
+        } else if self.format.is_binary() {
+            out.write_all(&self.format.encode(result)?)?;
+        } else {
+            writeln!(out, "{}", self.format.render(result)?)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+// In append mode (or with NDJSON) every result is appended as one JSON line, and CSV rows are always
+// appended under a single header; otherwise the file is atomically replaced with the latest result.
+// Appended text that fails to write is kept and retried with the next result. With max_size the
+// file is rotated before a write would take it past that size: `<path>.1` becomes `<path>.2` and
+// so on, the file becomes `<path>.1`, and only `keep` rotated files are kept. Every step is a
+// rename, so a line is always whole in exactly one file, and a failed rotation keeps the line
+// pending like a failed write.
+pub struct FileSink {
+    path: PathBuf,
+    format: OutputFormat,
+    append: bool,
+    max_size: Option<u64>,
+    keep: usize,
+    pending: RefCell<Vec<Vec<u8>>>,
+}
+
+impl FileSink {
+    pub fn new(path: PathBuf, format: OutputFormat, append: bool, max_size: Option<u64>, keep: usize) -> Self {
+        FileSink { path, format, append, max_size, keep: keep.max(1), pending: RefCell::new(Vec::new()) }
+    }
+
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(suffix);
+        PathBuf::from(name)
+    }
+
+    // A write bigger than max_size on its own still goes to a fresh file rather than being split
+    fn rotate_if_needed(&self, incoming: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(max_size) = self.max_size else {
+            return Ok(());
+        };
+        match fs::metadata(&self.path) {
+            Ok(metadata) if metadata.len() >= max_size || (metadata.len() > 0 && metadata.len() + incoming > max_size) => {
+                // The oldest is overwritten by the rename from the one before it
+                for index in (1..self.keep).rev() {
+                    let from = self.sibling(&format!(".{}", index));
+                    if from.exists() {
+                        fs::rename(&from, self.sibling(&format!(".{}", index + 1)))?;
+                    }
+                }
+                fs::rename(&self.path, self.sibling(".1"))?;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Text must end with a newline; binary documents are self-delimiting
+    fn append(&self, bytes: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut pending = self.pending.borrow_mut();
+        pending.push(bytes);
+        self.rotate_if_needed(pending.iter().map(|bytes| bytes.len() as u64).sum())?;
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        if self.format == OutputFormat::Csv && file.metadata()?.len() == 0 {
+            file.write_all(csv_rows(&Value::Null, true)?.as_bytes())?;
+        }
+        file.write_all(&pending.concat())?;
+        pending.clear();
+        Ok(())
+    }
+
+    // Readers only ever see the previous or the new complete file, never a partial write
+    fn replace(&self, rendered: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        self.rotate_if_needed(0)?;
+        let tmp_path = self.sibling(".tmp");
+        fs::write(&tmp_path, rendered)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl OutputSink for FileSink {
+    fn name(&self) -> String {
+        format!("file:{}", self.path.display())
+    }
+
+    async fn emit(&self, result: &Value) -> Result<(), Box<dyn std::error::Error>> {
+        if self.format == OutputFormat::Csv {
+            self.append(csv_rows(result, false)?.into_bytes())
+        } else if self.format.is_binary() {
+            let encoded = self.format.encode(result)?;
+            if self.append {
+                self.append(encoded)
+            } else {
+                self.replace(&encoded)
+            }
+        } else if self.append || self.format == OutputFormat::Ndjson {
+            self.append(format!("{}\n", OutputFormat::Ndjson.render(result)?).into_bytes())
+        } else {
+            self.replace(self.format.render(result)?.as_bytes())
+        }
+    }
+}
+
+// Attempts per result: the first POST plus one retry
+const WEBHOOK_ATTEMPTS: u32 = 2;
+
+pub struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(url: String, timeout: Duration) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(WebhookSink { url, client })
+    }
+
+    async fn post(&self, result: &Value) -> Result<(), Box<dyn std::error::Error>> {
+        let response = self.client.post(&self.url).json(result).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            warn!("webhook {} responded with {}", self.url, status);
+            return Err(format!("webhook responded with {}", status).into());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl OutputSink for WebhookSink {
+    fn name(&self) -> String {
+        format!("webhook:{}", self.url)
+    }
+
+    async fn emit(&self, result: &Value) -> Result<(), Box<dyn std::error::Error>> {
+        let mut attempt = 1;
+        loop {
+            match self.post(result).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < WEBHOOK_ATTEMPTS => {
+                    warn!("webhook delivery attempt {} failed: {}. Retrying...", attempt, e);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn csv_keywords_round_trip() {
+        let words = ["solana", "ünïcödé", "a,b", "say \"gm\""];
+        let result = json!({
+            "result": {
+                "timestamp": 1700000000,
+                "top_words": words.iter().map(|w| json!({ "word": w, "count": 2, "category": "word" })).collect::<Vec<_>>(),
+            }
+        });
+
+        let rendered = OutputFormat::Csv.render(&result).unwrap();
+        let mut reader = csv::Reader::from_reader(rendered.as_bytes());
+        assert_eq!(reader.headers().unwrap(), &csv::StringRecord::from(CSV_HEADER.to_vec()));
+        let keywords: Vec<String> = reader.records().map(|record| record.unwrap()[1].to_string()).collect();
+        assert_eq!(keywords, words);
+    }
+
+    #[test]
+    fn jsonl_stdout_is_one_json_object_per_batch() {
+        let buffer = SharedBuffer::default();
+        let sink = StdoutSink::with_writer("jsonl".parse().unwrap(), Box::new(buffer.clone()));
+        for batch in 0..3 {
+            block_on(sink.emit(&json!({ "most_common_word": "solana", "count": batch, "top_words": [] }))).unwrap();
+        }
+
+        let captured = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        let lines: Vec<&str> = captured.lines().collect();
+        assert_eq!(lines.len(), 3);
+        for (batch, line) in lines.iter().enumerate() {
+            let value: Value = serde_json::from_str(line).expect("every line is valid JSON");
+            assert_eq!(value["count"], batch);
+        }
+    }
+
+    #[test]
+    fn atomic_replace_never_exposes_a_torn_file() {
+        let dir = std::env::temp_dir().join(format!("solfhe-output-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("result.json");
+        let sink = FileSink::new(path.clone(), OutputFormat::Json, false, None, 1);
+        block_on(sink.emit(&json!({ "batch": 0 }))).unwrap();
+
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let (path, done) = (path.clone(), Arc::clone(&done));
+            thread::spawn(move || {
+                while !done.load(Ordering::SeqCst) {
+                    let contents = fs::read_to_string(&path).expect("output file disappeared");
+                    serde_json::from_str::<Value>(&contents).expect("torn output file");
+                }
+            })
+        };
+
+        // Large enough results that a non-atomic write would be observable mid-way
+        let words: Vec<String> = (0..5_000).map(|i| format!("word{}", i)).collect();
+        for batch in 1..=50 {
+            block_on(sink.emit(&json!({ "batch": batch, "top_words": words }))).unwrap();
+        }
+        done.store(true, Ordering::SeqCst);
+        reader.join().unwrap();
+
+        let last: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(last["batch"], 50);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn jsonl_rotation_keeps_whole_lines_in_the_newest_files() {
+        let dir = std::env::temp_dir().join(format!("solfhe-rotation-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("results.jsonl");
+        // Room for two of these lines per file
+        let line_size = format!("{}\n", json!({ "batch": 0 })).len() as u64;
+        let sink = FileSink::new(path.clone(), OutputFormat::Ndjson, true, Some(2 * line_size + 1), 2);
+        for batch in 0..7 {
+            block_on(sink.emit(&json!({ "batch": batch }))).unwrap();
+        }
+
+        let batches = |suffix: &str| -> Vec<u64> {
+            let mut name = path.clone().into_os_string();
+            name.push(suffix);
+            fs::read_to_string(PathBuf::from(name)).unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<Value>(line).unwrap()["batch"].as_u64().unwrap())
+                .collect()
+        };
+        assert_eq!(batches(""), [6]);
+        assert_eq!(batches(".1"), [4, 5]);
+        assert_eq!(batches(".2"), [2, 3]);
+        assert!(!dir.join("results.jsonl.3").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
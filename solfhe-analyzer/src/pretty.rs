@@ -0,0 +1,118 @@
+use std::fmt::Write;
+use std::io::{self, IsTerminal};
+
+use serde_json::Value;
+
+use crate::binary::payload_digest;
+
+const TOP_KEYWORDS: usize = 10;
+const BAR_WIDTH: usize = 20;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+
+// Colors only for a terminal, and never when NO_COLOR is set to anything non-empty (no-color.org)
+pub fn colors_enabled() -> bool {
+    io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", code, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+pub(crate) fn local_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+// Human readable summary of one result envelope: the batch window, the top keywords with
+// proportional bars and the digest of the compressed payload. A session result carries its own
+// window; otherwise the window starts at `window_start` (the previous batch), when known.
+pub fn render(envelope: &Value, window_start: Option<i64>, color: bool) -> String {
+    let style = Style { color };
+    let result = &envelope["result"];
+    let mut out = String::new();
+
+    let (start, end) = match result["session"].as_object() {
+        Some(session) => (session["start"].as_i64(), session["end"].as_i64()),
+        None => (window_start, result["timestamp"].as_i64()),
+    };
+    let window = match (start, end) {
+        (Some(start), Some(end)) => format!("{} → {}", local_time(start), local_time(end)),
+        (None, Some(end)) => format!("until {}", local_time(end)),
+        _ => "unknown window".to_string(),
+    };
+    let _ = writeln!(out, "{} {}", style.paint(BOLD, "Batch"), window);
+
+    let words: Vec<&Value> = result["top_words"].as_array().into_iter().flatten().take(TOP_KEYWORDS).collect();
+    if words.is_empty() {
+        let _ = writeln!(out, "  {}", style.paint(DIM, "no keywords in this batch"));
+    } else {
+        let name_width = words.iter()
+            .filter_map(|entry| entry["word"].as_str())
+            .map(|word| word.chars().count())
+            .fold("keyword".len(), usize::max);
+        let max_count = words.iter().filter_map(|entry| entry["count"].as_u64()).fold(1, u64::max) as usize;
+        let _ = writeln!(out, "  {}", style.paint(DIM, &format!("{:<name_width$}  {:>6}", "keyword", "count")));
+        for entry in words {
+            let word = entry["word"].as_str().unwrap_or_default();
+            let count = entry["count"].as_u64().unwrap_or(0) as usize;
+            let word_color = if entry["category"] == "network" { CYAN } else { BOLD };
+            // Rounded up so every listed keyword gets at least one marker
+            let bar = "█".repeat((count * BAR_WIDTH).div_ceil(max_count));
+            let _ = writeln!(
+                out,
+                "  {}  {}  {}",
+                style.paint(word_color, &format!("{:<name_width$}", word)),
+                style.paint(YELLOW, &format!("{:>6}", count)),
+                style.paint(GREEN, &bar),
+            );
+        }
+    }
+
+    // Hashed so the compressed payload fits on one line
+    let _ = write!(out, "{} {}", style.paint(DIM, "digest"), hex::encode(payload_digest(envelope)));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn plain_table_has_no_escape_codes_and_proportional_bars() {
+        let envelope = json!({
+            "encoding": "hex",
+            "result": {
+                "timestamp": 1700000600,
+                "top_words": [
+                    { "word": "solana", "count": 4, "category": "network" },
+                    { "word": "swap", "count": 2, "category": "word" },
+                ],
+            }
+        });
+
+        let table = render(&envelope, Some(1700000000), false);
+        assert!(!table.contains('\x1b'));
+        let bars: Vec<usize> = table.lines().skip(2).take(2).map(|line| line.matches('█').count()).collect();
+        assert_eq!(bars, [BAR_WIDTH, BAR_WIDTH / 2]);
+        assert!(table.lines().last().unwrap().ends_with(&hex::encode(payload_digest(&envelope))));
+        assert!(render(&envelope, None, true).contains(CYAN));
+    }
+}
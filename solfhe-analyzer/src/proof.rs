@@ -0,0 +1,339 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use ark_bn254::{Bn254, Fr};
+use ark_crypto_primitives::sponge::constraints::CryptographicSpongeVar;
+use ark_crypto_primitives::sponge::poseidon::constraints::PoseidonSpongeVar;
+use ark_crypto_primitives::sponge::poseidon::{find_poseidon_ark_and_mds, PoseidonConfig, PoseidonSponge};
+use ark_crypto_primitives::sponge::CryptographicSponge;
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_groth16::{Groth16, Proof, ProvingKey, VerifyingKey};
+use ark_r1cs_std::alloc::AllocVar;
+use ark_r1cs_std::boolean::Boolean;
+use ark_r1cs_std::eq::EqGadget;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::fields::FieldVar;
+use ark_r1cs_std::R1CSVar;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_snark::SNARK;
+use once_cell::sync::Lazy;
+use rand::rngs::OsRng;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::error::AnalyzerError;
+use crate::result::{TopKeywordProof, WordCount};
+use crate::state::state_dir;
+use crate::Config;
+
+// Keyword slots in the circuit. A result with more distinct keywords commits to its 32 most
+// counted ones, which always include the top keyword.
+pub const PROOF_SLOTS: usize = 32;
+// Every count is range checked to this many bits, which is what makes the `>=` relations sound
+const COUNT_BITS: usize = 32;
+
+// Poseidon over BN254 with rate 2, the x^5 S-box, 8 full and 57 partial rounds. The round
+// constants and MDS matrix are generated by arkworks' find_poseidon_ark_and_mds, not taken from
+// the reference instances (circomlib, poseidon-rs), and arkworks' sponge lays out its state
+// differently too: same shape, different permutation. Digests only match arkworks, natively or in
+// its R1CS gadget.
+pub(crate) static ARKWORKS_POSEIDON: Lazy<PoseidonConfig<Fr>> = Lazy::new(|| {
+    let (ark, mds) = find_poseidon_ark_and_mds::<Fr>(Fr::MODULUS_BIT_SIZE as u64, 2, 8, 57, 0);
+    PoseidonConfig::new(8, 57, 5, mds, ark, 2, 1)
+});
+
+fn proving_key_path() -> PathBuf {
+    state_dir().join("zk-proving-key.bin")
+}
+
+pub fn verifying_key_path() -> PathBuf {
+    state_dir().join("zk-verifying-key.bin")
+}
+
+// SHA-256 of the keyword, reduced into the field
+pub fn keyword_hash(word: &str) -> Fr {
+    Fr::from_le_bytes_mod_order(&Sha256::digest(word.as_bytes()))
+}
+
+fn commit(blinding: Fr, hashes: &[Fr], counts: &[u32]) -> Fr {
+    let mut inputs = vec![blinding];
+    for (&hash, &count) in hashes.iter().zip(counts) {
+        inputs.push(hash);
+        inputs.push(Fr::from(count));
+    }
+    let mut sponge = PoseidonSponge::new(&ARKWORKS_POSEIDON);
+    sponge.absorb(&inputs);
+    sponge.squeeze_field_elements::<Fr>(1)[0]
+}
+
+fn field_hex(value: &Fr) -> String {
+    let mut bytes = Vec::new();
+    value.serialize_compressed(&mut bytes).expect("serializing into a Vec cannot fail");
+    hex::encode(bytes)
+}
+
+// Proves that the `top` slot holds `keyword` with the largest count of all slots, and that this
+// count is at least `threshold`, without revealing the other slots:
+//   commitment == Poseidon(blinding, hash_0, count_0, ..., hash_31, count_31)
+//   exactly one selector bit is set, and the selected slot's hash is `keyword`
+//   every count_i, selected - count_i and selected - threshold fit in COUNT_BITS bits
+// Public inputs are the commitment, the keyword hash and the threshold, in that order.
+#[derive(Clone)]
+struct TopKeywordCircuit {
+    commitment: Fr,
+    keyword: Fr,
+    threshold: u32,
+    blinding: Fr,
+    hashes: Vec<Fr>,
+    counts: Vec<u32>,
+    top: usize,
+}
+
+impl TopKeywordCircuit {
+    // Claims that `words[top]` is the top keyword with a count of at least `threshold`
+    fn new(words: &[WordCount], top: usize, threshold: u32) -> TopKeywordCircuit {
+        let mut hashes = vec![Fr::from(0u64); PROOF_SLOTS];
+        let mut counts = vec![0; PROOF_SLOTS];
+        for (slot, word) in words.iter().take(PROOF_SLOTS).enumerate() {
+            hashes[slot] = keyword_hash(&word.word);
+            counts[slot] = word.count;
+        }
+        let blinding = Fr::rand(&mut OsRng);
+        TopKeywordCircuit {
+            commitment: commit(blinding, &hashes, &counts),
+            keyword: keyword_hash(&words[top].word),
+            threshold,
+            blinding,
+            hashes,
+            counts,
+            top,
+        }
+    }
+
+    // The setup only needs the circuit's shape
+    fn blank() -> TopKeywordCircuit {
+        TopKeywordCircuit::new(&[WordCount { word: String::new(), count: 0, category: None }], 0, 0)
+    }
+}
+
+fn enforce_count_bits(cs: &ConstraintSystemRef<Fr>, value: &FpVar<Fr>) -> Result<(), SynthesisError> {
+    let bits = (0..COUNT_BITS)
+        .map(|bit| Boolean::new_witness(cs.clone(), || value.value().map(|value| value.into_bigint().get_bit(bit))))
+        .collect::<Result<Vec<_>, _>>()?;
+    Boolean::le_bits_to_fp_var(&bits)?.enforce_equal(value)
+}
+
+impl ConstraintSynthesizer<Fr> for TopKeywordCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let commitment = FpVar::new_input(cs.clone(), || Ok(self.commitment))?;
+        let keyword = FpVar::new_input(cs.clone(), || Ok(self.keyword))?;
+        let threshold = FpVar::new_input(cs.clone(), || Ok(Fr::from(self.threshold)))?;
+        let blinding = FpVar::new_witness(cs.clone(), || Ok(self.blinding))?;
+        let hashes = self.hashes.iter()
+            .map(|&hash| FpVar::new_witness(cs.clone(), || Ok(hash)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let counts = self.counts.iter()
+            .map(|&count| FpVar::new_witness(cs.clone(), || Ok(Fr::from(count))))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut selectors = FpVar::zero();
+        let mut selected_hash = FpVar::zero();
+        let mut selected_count = FpVar::zero();
+        for (slot, (hash, count)) in hashes.iter().zip(&counts).enumerate() {
+            let selector: FpVar<Fr> = Boolean::new_witness(cs.clone(), || Ok(slot == self.top))?.into();
+            selected_hash += &selector * hash;
+            selected_count += &selector * count;
+            selectors += selector;
+            enforce_count_bits(&cs, count)?;
+        }
+        selectors.enforce_equal(&FpVar::one())?;
+        selected_hash.enforce_equal(&keyword)?;
+        for count in &counts {
+            enforce_count_bits(&cs, &(&selected_count - count))?;
+        }
+        enforce_count_bits(&cs, &(&selected_count - &threshold))?;
+
+        let mut inputs = vec![blinding];
+        for (hash, count) in hashes.into_iter().zip(counts) {
+            inputs.push(hash);
+            inputs.push(count);
+        }
+        let mut sponge = PoseidonSpongeVar::new(cs.clone(), &ARKWORKS_POSEIDON);
+        sponge.absorb(&inputs)?;
+        sponge.squeeze_field_elements(1)?[0].enforce_equal(&commitment)
+    }
+}
+
+pub struct TopKeywordProver {
+    proving_key: ProvingKey<Bn254>,
+}
+
+impl TopKeywordProver {
+    // Fresh Groth16 keys. Whoever runs the setup could forge proofs, so a proof only convinces
+    // someone who trusts the machine that generated the verifying key.
+    fn setup() -> Result<(TopKeywordProver, VerifyingKey<Bn254>), SynthesisError> {
+        let (proving_key, verifying_key) = Groth16::<Bn254>::circuit_specific_setup(TopKeywordCircuit::blank(), &mut OsRng)?;
+        Ok((TopKeywordProver { proving_key }, verifying_key))
+    }
+
+    // Keys are generated on first use and kept in the state directory
+    pub fn load_or_setup() -> Result<TopKeywordProver, Box<dyn std::error::Error>> {
+        if let Ok(bytes) = fs::read(proving_key_path()) {
+            let proving_key = ProvingKey::deserialize_uncompressed_unchecked(&bytes[..])
+                .map_err(|e| format!("{}: {}", proving_key_path().display(), e))?;
+            return Ok(TopKeywordProver { proving_key });
+        }
+
+        info!("Generating the Groth16 keys for top-keyword proofs, this happens once");
+        let (prover, verifying_key) = TopKeywordProver::setup()?;
+        fs::create_dir_all(state_dir())?;
+        let mut bytes = Vec::new();
+        verifying_key.serialize_compressed(&mut bytes)?;
+        fs::write(verifying_key_path(), &bytes)?;
+        bytes.clear();
+        prover.proving_key.serialize_uncompressed(&mut bytes)?;
+        fs::write(proving_key_path(), &bytes)?;
+        Ok(prover)
+    }
+
+    // `top_words` sorted by count, most counted first; None without any keyword
+    pub fn prove(&self, top_words: &[WordCount]) -> Result<Option<TopKeywordProof>, Box<dyn std::error::Error>> {
+        let Some(top) = top_words.first() else {
+            return Ok(None);
+        };
+        let circuit = TopKeywordCircuit::new(top_words, 0, top.count);
+        let commitment = field_hex(&circuit.commitment);
+
+        let started = Instant::now();
+        let proof = Groth16::<Bn254>::prove(&self.proving_key, circuit, &mut OsRng)?;
+        let proving_millis = started.elapsed().as_millis() as u64;
+
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes)?;
+        Ok(Some(TopKeywordProof {
+            keyword: top.word.clone(),
+            threshold: top.count,
+            commitment,
+            slots: PROOF_SLOTS,
+            proof_bytes: bytes.len(),
+            proof: hex::encode(bytes),
+            proving_millis,
+        }))
+    }
+}
+
+pub fn verify(proof: &TopKeywordProof, verifying_key: &VerifyingKey<Bn254>) -> Result<(), String> {
+    if proof.slots != PROOF_SLOTS {
+        return Err(format!("proof for {} slots, this circuit has {}", proof.slots, PROOF_SLOTS));
+    }
+    let commitment = hex::decode(&proof.commitment)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| Fr::deserialize_compressed(&bytes[..]).map_err(|e| e.to_string()))
+        .map_err(|e| format!("invalid commitment: {}", e))?;
+    let groth16_proof = hex::decode(&proof.proof)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| Proof::<Bn254>::deserialize_compressed(&bytes[..]).map_err(|e| e.to_string()))
+        .map_err(|e| format!("invalid proof: {}", e))?;
+
+    let inputs = [commitment, keyword_hash(&proof.keyword), Fr::from(proof.threshold)];
+    match Groth16::<Bn254>::verify(verifying_key, &inputs, &groth16_proof) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("the proof does not verify".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Backs the `verify-proof` subcommand: checks the top-keyword proof of every result in a JSON or
+// JSON Lines file against --verifying-key (by default this machine's key), and that a readable
+// result claims the same top keyword and count
+pub fn run_verify_proof(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config.verify_proof_input.as_ref().ok_or_else(|| AnalyzerError::Usage("verify-proof requires a file path".to_string()))?;
+    let key_path = config.verifying_key.clone().unwrap_or_else(verifying_key_path);
+    let verifying_key = fs::read(&key_path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| VerifyingKey::<Bn254>::deserialize_compressed(&bytes[..]).map_err(|e| e.to_string()))
+        .map_err(|e| AnalyzerError::Usage(format!("verifying key {}: {}", key_path.display(), e)))?;
+
+    let contents = fs::read_to_string(path).map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e)))?;
+    let documents: Vec<Value> = match serde_json::from_str(&contents) {
+        Ok(document) => vec![document],
+        Err(_) => contents.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e)))?,
+    };
+
+    let mut failures = Vec::new();
+    for (index, document) in documents.iter().enumerate() {
+        let checked = serde_json::from_value::<TopKeywordProof>(document["top_keyword_proof"].clone())
+            .map_err(|_| "no top_keyword_proof".to_string())
+            .and_then(|proof| {
+                let result = &document["result"];
+                if result["most_common_word"].as_str().is_some_and(|word| word != proof.keyword)
+                    || result["count"].as_u64().is_some_and(|count| count != u64::from(proof.threshold))
+                {
+                    return Err(format!("the proof is about `{}`, not the result's top keyword", proof.keyword));
+                }
+                verify(&proof, &verifying_key).map(|()| proof)
+            });
+        match checked {
+            Ok(proof) => println!(
+                "result {}: valid proof that `{}` was the top keyword with a count of at least {}",
+                index + 1,
+                proof.keyword,
+                proof.threshold
+            ),
+            Err(e) => failures.push(format!("result {}: {}", index + 1, e)),
+        }
+    }
+    if failures.is_empty() {
+        return Ok(());
+    }
+    Err(AnalyzerError::InvalidProof(failures.join("; ")).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    fn words(counts: &[(&str, u32)]) -> Vec<WordCount> {
+        counts.iter().map(|&(word, count)| WordCount { word: word.to_string(), count, category: None }).collect()
+    }
+
+    fn satisfied(circuit: TopKeywordCircuit) -> bool {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn circuit_accepts_only_the_true_maximum() {
+        let batch = words(&[("solana", 5), ("swap", 3), ("staking", 5)]);
+        assert!(satisfied(TopKeywordCircuit::new(&batch, 0, 5)));
+        assert!(satisfied(TopKeywordCircuit::new(&batch, 0, 4)));
+        // Ties are both the top keyword
+        assert!(satisfied(TopKeywordCircuit::new(&batch, 2, 5)));
+        assert!(!satisfied(TopKeywordCircuit::new(&batch, 1, 3)));
+        assert!(!satisfied(TopKeywordCircuit::new(&batch, 0, 6)));
+    }
+
+    #[test]
+    fn proofs_verify_only_for_their_claim() {
+        let (prover, verifying_key) = TopKeywordProver::setup().unwrap();
+        let proof = prover.prove(&words(&[("solana", 5), ("swap", 3)])).unwrap().unwrap();
+        assert_eq!((proof.keyword.as_str(), proof.threshold, proof.slots), ("solana", 5, PROOF_SLOTS));
+        assert_eq!(verify(&proof, &verifying_key), Ok(()));
+
+        let swapped = TopKeywordProof { keyword: "swap".to_string(), ..proof.clone() };
+        assert!(verify(&swapped, &verifying_key).is_err());
+        let inflated = TopKeywordProof { threshold: 6, ..proof.clone() };
+        assert!(verify(&inflated, &verifying_key).is_err());
+        let (_, other_key) = TopKeywordProver::setup().unwrap();
+        assert!(verify(&proof, &other_key).is_err());
+    }
+}
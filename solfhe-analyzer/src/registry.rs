@@ -0,0 +1,203 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::client_error::ClientError;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::system_program;
+
+use crate::error::AnalyzerError;
+use crate::native::current_thread_runtime;
+use crate::Config;
+use crate::submit::{load_submit_keypair, send, SubmitOptions};
+
+// Client for the result registry, an Anchor program that keeps one account per attested batch.
+// The account of a batch is the PDA of ("result", wallet, batch counter), so a wallet's results
+// can be enumerated by anyone and a batch can't be stored twice.
+const RESULT_SEED: &[u8] = b"result";
+
+// The Anchor layout of a StoredResult account: discriminator, wallet, batch counter (u64 LE),
+// schema version (u32 LE), SHA-256 of the canonical payload and the slot time it was stored at
+// (i64 LE, set by the program)
+pub const RESULT_ACCOUNT_SIZE: usize = 8 + 32 + 8 + 4 + 32 + 8;
+const WALLET_OFFSET: usize = 8;
+
+// Anchor's 8-byte discriminators: the first bytes of sha256("global:<instruction>") for
+// instructions and of sha256("account:<Account>") for account data
+fn discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("{}:{}", namespace, name));
+    hash[..8].try_into().expect("SHA-256 is longer than 8 bytes")
+}
+
+pub fn result_address(program_id: &Pubkey, wallet: &Pubkey, batch_counter: u64) -> Pubkey {
+    Pubkey::find_program_address(&[RESULT_SEED, wallet.as_ref(), &batch_counter.to_le_bytes()], program_id).0
+}
+
+// `store_result(batch_counter: u64, schema_version: u32, digest: [u8; 32])`. The wallet signs and
+// pays the rent of the new account, which the program creates with `init`.
+pub fn store_result_instruction(program_id: &Pubkey, wallet: &Pubkey, batch_counter: u64, schema_version: u32, digest: &[u8; 32]) -> Instruction {
+    let mut data = discriminator("global", "store_result").to_vec();
+    data.extend_from_slice(&batch_counter.to_le_bytes());
+    data.extend_from_slice(&schema_version.to_le_bytes());
+    data.extend_from_slice(digest);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(result_address(program_id, wallet, batch_counter), false),
+            AccountMeta::new(*wallet, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data,
+    }
+}
+
+// One stored batch, as `onchain-results` lists it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StoredResult {
+    pub address: String,
+    pub wallet: String,
+    pub batch_counter: u64,
+    pub schema_version: u32,
+    pub digest: String,
+    pub stored_at: i64,
+}
+
+// None for data that isn't a StoredResult account (another account type of the program)
+pub fn decode_result(address: &Pubkey, data: &[u8]) -> Option<StoredResult> {
+    if data.len() != RESULT_ACCOUNT_SIZE || data[..8] != discriminator("account", "StoredResult") {
+        return None;
+    }
+    let wallet = Pubkey::try_from(&data[8..40]).ok()?;
+    Some(StoredResult {
+        address: address.to_string(),
+        wallet: wallet.to_string(),
+        batch_counter: u64::from_le_bytes(data[40..48].try_into().ok()?),
+        schema_version: u32::from_le_bytes(data[48..52].try_into().ok()?),
+        digest: hex::encode(&data[52..84]),
+        stored_at: i64::from_le_bytes(data[84..92].try_into().ok()?),
+    })
+}
+
+pub enum Stored {
+    Created(Signature),
+    // The account already held this digest, e.g. when a confirmation was lost and the result is
+    // submitted again
+    AlreadyStored,
+}
+
+// Stores a batch digest in its result account and returns the account address. An account that
+// exists with a different digest is an error rather than overwritten: the batch counter was
+// reused, most likely after the counter file was lost (see `counter_reset`).
+pub async fn store_result(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    payer: &Keypair,
+    batch_counter: u64,
+    schema_version: u32,
+    digest: &[u8; 32],
+    options: &SubmitOptions,
+) -> Result<(Pubkey, Stored), Box<dyn std::error::Error>> {
+    let address = result_address(program_id, &payer.pubkey(), batch_counter);
+    if let Some(account) = client.get_account_with_commitment(&address, client.commitment()).await?.value {
+        let existing = decode_result(&address, &account.data)
+            .ok_or_else(|| format!("result account {} exists but is not a stored result", address))?;
+        if existing.digest == hex::encode(digest) {
+            return Ok((address, Stored::AlreadyStored));
+        }
+        return Err(format!(
+            "result account {} for batch counter {} already holds digest {}",
+            address, batch_counter, existing.digest
+        )
+        .into());
+    }
+
+    // Checked up front so an underfunded wallet gets a clear error instead of a failed simulation
+    let instruction = store_result_instruction(program_id, &payer.pubkey(), batch_counter, schema_version, digest);
+    let instructions = options.budget(client, &payer.pubkey(), &[instruction]).await?;
+    let blockhash = client.get_latest_blockhash().await?;
+    let message = Message::new_with_blockhash(&instructions, Some(&payer.pubkey()), &blockhash);
+    let rent = client.get_minimum_balance_for_rent_exemption(RESULT_ACCOUNT_SIZE).await?;
+    let fee = client.get_fee_for_message(&message).await?;
+    let balance = client.get_balance(&payer.pubkey()).await?;
+    if balance < rent + fee {
+        return Err(format!(
+            "{} holds {} lamports, storing a result needs {} for rent and {} for the fee",
+            payer.pubkey(),
+            balance,
+            rent,
+            fee
+        )
+        .into());
+    }
+
+    let signature = send(client, payer, &instructions).await?;
+    Ok((address, Stored::Created(signature)))
+}
+
+// Every result stored for `wallet`, oldest batch first
+pub async fn fetch_results(client: &RpcClient, program_id: &Pubkey, wallet: &Pubkey) -> Result<Vec<StoredResult>, ClientError> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::DataSize(RESULT_ACCOUNT_SIZE as u64),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(WALLET_OFFSET, wallet.as_ref())),
+        ]),
+        account_config: RpcAccountInfoConfig { encoding: Some(UiAccountEncoding::Base64), ..RpcAccountInfoConfig::default() },
+        ..RpcProgramAccountsConfig::default()
+    };
+    let accounts = client.get_program_accounts_with_config(program_id, config).await?;
+    let mut results: Vec<StoredResult> = accounts.iter().filter_map(|(address, account)| decode_result(address, &account.data)).collect();
+    results.sort_by_key(|result| result.batch_counter);
+    Ok(results)
+}
+
+// Backs `onchain-results [WALLET]`: prints the wallet's stored results as JSON lines. The wallet
+// defaults to the --keypair (or SOLANA_KEYPAIR) public key.
+pub fn run_onchain_results(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let program_id = config.program_id.ok_or_else(|| AnalyzerError::Usage("onchain-results requires --program-id".to_string()))?;
+    let wallet: Pubkey = match &config.onchain_wallet {
+        Some(wallet) => wallet.parse().map_err(|e| AnalyzerError::Usage(format!("invalid wallet {}: {}", wallet, e)))?,
+        None => load_submit_keypair(config.keypair.as_deref())?.pubkey(),
+    };
+    let client = config.rpc_client();
+    let results = current_thread_runtime()?
+        .block_on(fetch_results(&client, &program_id, &wallet))
+        .map_err(|e| AnalyzerError::Extraction(format!("reading the results of {}: {}", wallet, e)))?;
+    for result in results {
+        println!("{}", serde_json::to_string(&result)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_result_round_trips_through_the_account_layout() {
+        let program_id = Pubkey::new_unique();
+        let wallet = Pubkey::new_unique();
+        let digest = [7u8; 32];
+        let instruction = store_result_instruction(&program_id, &wallet, 42, 21, &digest);
+        assert_eq!(instruction.accounts[0].pubkey, result_address(&program_id, &wallet, 42));
+        assert_ne!(result_address(&program_id, &wallet, 42), result_address(&program_id, &wallet, 43));
+        assert_eq!(instruction.data.len(), 8 + 8 + 4 + 32);
+
+        let mut data = discriminator("account", "StoredResult").to_vec();
+        data.extend_from_slice(wallet.as_ref());
+        data.extend_from_slice(&instruction.data[8..]);
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        let address = instruction.accounts[0].pubkey;
+        let stored = decode_result(&address, &data).unwrap();
+        assert_eq!((stored.batch_counter, stored.schema_version, stored.stored_at), (42, 21, 1_700_000_000));
+        assert_eq!(stored.wallet, wallet.to_string());
+        assert_eq!(stored.digest, hex::encode(digest));
+
+        data[0] ^= 1;
+        assert_eq!(decode_result(&address, &data), None);
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt::Write;
+
+use crate::result::{KeywordCategory, ResultEnvelope};
+
+const CATEGORIES: [KeywordCategory; 3] = [KeywordCategory::Network, KeywordCategory::Label, KeywordCategory::Word];
+
+// Backslash-escapes every character Markdown could interpret in inline text or a table cell
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_{}[]()<>#+-.!|~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn utc(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+// Shareable summary of one result. Everything comes from the envelope that is also serialized as
+// JSON, so the two can't disagree; `digest` is the hex payload digest for verification.
+pub fn render_markdown(envelope: &ResultEnvelope, digest: &str, category_sections: bool) -> String {
+    let result = &envelope.result;
+    let mut out = String::new();
+
+    let window = match (&result.window, &result.session) {
+        (Some(window), _) => format!("{} – {}", utc(window.start), utc(window.end)),
+        (None, Some(session)) => format!("{} – {}", utc(session.start), utc(session.end)),
+        (None, None) => format!("until {}", utc(result.timestamp)),
+    };
+    let _ = writeln!(out, "# Solfhe Analyzer report\n");
+    let _ = writeln!(out, "- **Analysis window:** {}", window);
+    let _ = writeln!(out, "- **URLs processed:** {}", result.links_analyzed);
+    let _ = writeln!(
+        out,
+        "- **Generated:** {} by solfhe-analyzer {} (schema {})",
+        envelope.generated_at, envelope.analyzer_version, envelope.schema_version
+    );
+    let _ = writeln!(out, "- **Digest:** `{}` (SHA-256 of the {} payload)", digest, envelope.encoding);
+
+    let _ = writeln!(out, "\n## Top keywords\n");
+    if result.top_words.is_empty() {
+        let _ = writeln!(out, "_No keywords._");
+    } else {
+        let _ = writeln!(out, "| # | Keyword | Count | Category |\n|--:|---|--:|---|");
+        for (rank, word) in result.top_words.iter().enumerate() {
+            let category = word.category.unwrap_or(KeywordCategory::Word);
+            let _ = writeln!(out, "| {} | {} | {} | {} |", rank + 1, escape(&word.word), word.count, category.name());
+        }
+    }
+
+    // Left empty in privacy mode
+    let _ = writeln!(out, "\n## Top domains\n");
+    if result.top_domains.is_empty() {
+        let _ = writeln!(out, "_No domains recorded._");
+    } else {
+        let _ = writeln!(out, "| Domain | Links |\n|---|--:|");
+        for domain in &result.top_domains {
+            let _ = writeln!(out, "| {} | {} |", escape(&domain.domain), domain.count);
+        }
+    }
+
+    let _ = writeln!(out, "\n## Categories\n");
+    let _ = writeln!(out, "| Category | Count |\n|---|--:|");
+    for category in CATEGORIES {
+        let count = result.category_counts.get(category.name()).copied().unwrap_or(0);
+        let _ = writeln!(out, "| {} | {} |", category.name(), count);
+    }
+
+    if category_sections {
+        for category in CATEGORIES {
+            let words: Vec<_> = result.top_words.iter().filter(|word| word.category == Some(category)).collect();
+            if words.is_empty() {
+                continue;
+            }
+            let name = category.name();
+            let _ = writeln!(out, "\n### {}{}\n", name[..1].to_uppercase(), &name[1..]);
+            for word in words {
+                let _ = writeln!(out, "- {} ({})", escape(&word.word), word.count);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn keywords_are_escaped_inside_the_table() {
+        assert_eq!(escape("a|b_c"), r"a\|b\_c");
+
+        let envelope: ResultEnvelope = serde_json::from_value(json!({
+            "schema_version": 4,
+            "generated_at": "2024-05-01T12:00:00Z",
+            "analyzer_version": "0.1.0",
+            "batch_size": 5,
+            "encoding": "base64",
+            "source": { "browser": "chrome", "profile": "Default" },
+            "result": {
+                "count": 2,
+                "timestamp": 1714564800,
+                "links_analyzed": 3,
+                "top_words": [{ "word": "sol|ana_x", "count": 2, "category": "word" }],
+            }
+        }))
+        .unwrap();
+
+        let report = render_markdown(&envelope, "00", false);
+        assert!(report.lines().any(|line| line == r"| 1 | sol\|ana\_x | 2 | word |"));
+        assert!(!report.contains("### "));
+        assert!(render_markdown(&envelope, "00", true).contains("### Word\n\n- sol\\|ana\\_x (2)"));
+    }
+}
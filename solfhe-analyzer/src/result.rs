@@ -0,0 +1,316 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub use crate::analysis::{KeywordCategory, WordCount};
+use crate::compression::CompressionBackend;
+use crate::digest::legacy_canonicalization;
+use crate::encoding::Encoding;
+use crate::hashing::HashFunction;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WordDelta {
+    pub word: String,
+    pub delta: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WordScore {
+    pub word: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UrlDetail {
+    pub url: String,
+    pub keywords: Vec<String>,
+    pub matched_networks: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairCount {
+    pub pair: [String; 2],
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Trends {
+    pub new: Vec<WordCount>,
+    pub increased: Vec<WordDelta>,
+    pub decreased: Vec<WordDelta>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub most_common_word: Option<String>,
+    pub count: u32,
+    pub word_counts: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainCount {
+    pub domain: String,
+    pub count: u32,
+}
+
+// Where the time of a batch went, with --timings: reading the history databases (copy and
+// query) against scoring the links. Summed over every poll that fed the batch.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    pub extract_micros: u64,
+    pub urls_extracted: usize,
+    pub analyze_micros: u64,
+}
+
+// First and last visit (unix seconds) among the links of a history scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VisitWindow {
+    pub start: i64,
+    pub end: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub start: i64,
+    pub end: i64,
+    pub duration_secs: i64,
+}
+
+// One emitted analysis. `most_common_word` is None when the batch produced no keywords.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisResult {
+    pub most_common_word: Option<String>,
+    pub count: u32,
+    pub timestamp: i64,
+    // Short hash of the interest profile, see AnalysisResult::fingerprint
+    #[serde(default)]
+    pub fingerprint: String,
+    // Links that made it into this result, after exclusions and deduplication
+    #[serde(default)]
+    pub links_analyzed: usize,
+    pub top_words: Vec<WordCount>,
+    // Full keyword distribution; a BTreeMap so identical input always serializes (and hashes) identically
+    #[serde(default)]
+    pub word_counts: BTreeMap<String, u32>,
+    // Counts of the recognized blockchain networks only, highest first, so there is a
+    // crypto-focused view whatever the most common word is
+    #[serde(default)]
+    pub networks_summary: Vec<WordCount>,
+    // How broadly each network was explored: distinct domains rather than raw hits
+    #[serde(default)]
+    pub network_domains: BTreeMap<String, usize>,
+    // Most visited registrable domains; left empty in privacy mode
+    #[serde(default)]
+    pub top_domains: Vec<DomainCount>,
+    // Summed counts per keyword category, keyed by category name
+    #[serde(default)]
+    pub category_counts: BTreeMap<String, u32>,
+    // Only with --domains: links per organization, the registrable domain without its public
+    // suffix (`example` for both example.com and example.co.uk); left out in privacy mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domains: Option<BTreeMap<String, u32>>,
+    #[serde(default)]
+    pub cooccurrence: Vec<PairCount>,
+    #[serde(default)]
+    pub trends: Trends,
+    // Only with --fhe emit: word -> TFHE ciphertext of its count (base64 bincode), see fhe.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_counts: Option<BTreeMap<String, String>>,
+    // Only with --deltas: word -> count change since the previous batch, see compute_deltas
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta: Option<BTreeMap<String, i64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub analyzed_urls: Option<Vec<UrlDetail>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<Vec<BatchSummary>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_words_tfidf: Option<Vec<WordScore>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<VisitWindow>,
+    // The --backfill result covering the history before the first poll
+    #[serde(default)]
+    pub baseline: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Timings>,
+    // Salted hashes of the analyzed links, the leaves of the envelope's url_root. Kept out of the
+    // output; the results database stores them for prove-inclusion.
+    #[serde(skip)]
+    pub url_hashes: Vec<String>,
+}
+
+// Hex characters kept from the digest; 64 bits is plenty to tell profiles apart
+const FINGERPRINT_LEN: usize = 16;
+
+impl AnalysisResult {
+    // Hash of the top words sorted alphabetically, each with its share of the top words' total
+    // count. Shares are rounded to four decimals so the canonical form never depends on float
+    // formatting, and absolute volume doesn't matter: the same interests at twice the browsing
+    // give the same fingerprint. Hashed with the configured --hash, so fingerprints are only
+    // comparable between results that name the same one.
+    pub fn fingerprint(&self, hash: HashFunction) -> String {
+        let total: u32 = self.top_words.iter().map(|word| word.count).sum();
+        let mut words: Vec<&WordCount> = self.top_words.iter().collect();
+        words.sort_by(|a, b| a.word.cmp(&b.word));
+
+        let mut canonical = String::new();
+        for word in words {
+            let share = if total == 0 { 0.0 } else { word.count as f64 / total as f64 };
+            canonical.push_str(&format!("{}\t{:.4}\n", word.word, share));
+        }
+        let mut fingerprint = hex::encode(hash.digest(canonical.as_bytes()));
+        fingerprint.truncate(FINGERPRINT_LEN);
+        fingerprint
+    }
+
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).expect("AnalysisResult always serializes to JSON")
+    }
+}
+
+// Bump whenever the shape of ResultEnvelope or anything inside AnalysisResult changes
+pub const SCHEMA_VERSION: u32 = 24;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResultSource {
+    pub browser: String,
+    pub profile: String,
+}
+
+// What the "ZK compression" of one result cost and bought. Measured on the payload that goes
+// on-chain, which is the envelope without these stats.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompressionStats {
+    pub original_bytes: usize,
+    pub compressed_bytes: usize,
+    // compressed_bytes / original_bytes
+    pub ratio: f64,
+    pub compress_micros: u64,
+    pub decompress_micros: u64,
+    // SHA-256 (hex) of the uncompressed payload, to check a decompressed memo against
+    pub sha256: String,
+    // Only with --hash poseidon: Poseidon digest (hex) of the uncompressed payload, for circuits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<String>,
+}
+
+// Groth16 (BN254) proof that `keyword` had the highest count, at least `threshold`, among the
+// `slots` most counted keywords of the result, which are committed to in `commitment` (a Poseidon
+// hash, hex) without being revealed; see proof.rs and the `verify-proof` subcommand
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopKeywordProof {
+    pub keyword: String,
+    pub threshold: u32,
+    pub commitment: String,
+    // Compressed proof, hex
+    pub proof: String,
+    pub slots: usize,
+    pub proof_bytes: usize,
+    pub proving_millis: u64,
+}
+
+// What every sink and the on-chain memo receive: the analysis plus enough run metadata to compare
+// results across machines and analyzer versions. `hostname` is left out in privacy mode. With
+// --encrypt, `result` is replaced by `encrypted_result` before the envelope leaves the analyzer
+// (see encryption::seal), so only the plain form deserializes into this struct.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResultEnvelope {
+    pub schema_version: u32,
+    pub generated_at: String,
+    pub analyzer_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    pub batch_size: usize,
+    // How the on-chain memo (and the stored compressed payload) is encoded
+    pub encoding: Encoding,
+    // Backend that compressed the on-chain payload; the payload's first byte names it as well
+    #[serde(default)]
+    pub compression: CompressionBackend,
+    // How the envelope was serialized for compression, see digest.rs
+    #[serde(default = "legacy_canonicalization")]
+    pub canonicalization: u32,
+    // Short hash of the zstd dictionary the payload was compressed with, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression_dictionary: Option<String>,
+    pub source: ResultSource,
+    // Non-fatal issues since the previous result, e.g. a skipped history source or a failed sink
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    #[serde(default)]
+    pub errors: Vec<String>,
+    // Only on emitted batch results, and left out with --no-compression-stats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression_stats: Option<CompressionStats>,
+    // Only with --prove
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_keyword_proof: Option<TopKeywordProof>,
+    // Hash function of url_root and the payload commitment; sha256 before it was recorded
+    #[serde(default)]
+    pub hash: HashFunction,
+    // Merkle root (hex) over the salted hashes of the analyzed links, see merkle.rs
+    #[serde(default)]
+    pub url_root: String,
+    // Replay protection, covered by the payload digest and the signature like everything else:
+    // this installation's batch counter (0 before it existed), whether it started over at this
+    // envelope, and a random 128-bit nonce (hex); see state::next_batch_counter
+    #[serde(default)]
+    pub counter: u64,
+    #[serde(default)]
+    pub counter_reset: bool,
+    #[serde(default)]
+    pub nonce: String,
+    // ed25519 signature (base58) over the canonical envelope without these two fields, and the
+    // signer's public key (base58); see the `verify` subcommand
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    // Only with --submit: the signature (base58) of the memo transaction that anchored the
+    // payload's digest; added after compression like compression_stats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo_signature: Option<String>,
+    // The Solana cluster the result is attested on (see --cluster), or `custom`
+    #[serde(default)]
+    pub cluster: String,
+    pub result: AnalysisResult,
+}
+
+impl ResultEnvelope {
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).expect("ResultEnvelope always serializes to JSON")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn result(top_words: Value) -> AnalysisResult {
+        serde_json::from_value(json!({ "count": 0, "timestamp": 0, "top_words": top_words })).unwrap()
+    }
+
+    #[test]
+    fn fingerprint_depends_on_relative_interests_only() {
+        let profile = result(json!([{ "word": "solana", "count": 4 }, { "word": "swap", "count": 2 }]));
+        let reordered = result(json!([{ "word": "swap", "count": 2 }, { "word": "solana", "count": 4 }]));
+        let doubled = result(json!([{ "word": "solana", "count": 8 }, { "word": "swap", "count": 4 }]));
+        let shifted = result(json!([{ "word": "solana", "count": 4 }, { "word": "swap", "count": 3 }]));
+
+        for hash in [HashFunction::Sha256, HashFunction::Poseidon] {
+            assert_eq!(profile.fingerprint(hash).len(), FINGERPRINT_LEN);
+            assert_eq!(profile.fingerprint(hash), reordered.fingerprint(hash));
+            assert_eq!(profile.fingerprint(hash), doubled.fingerprint(hash));
+            assert_ne!(profile.fingerprint(hash), shifted.fingerprint(hash));
+        }
+    }
+
+    #[test]
+    fn fingerprint_follows_the_configured_hash() {
+        let profile = result(json!([{ "word": "solana", "count": 4 }, { "word": "swap", "count": 2 }]));
+        assert_eq!(profile.fingerprint(HashFunction::Sha256), "aa189a2d9963e673");
+        assert_eq!(profile.fingerprint(HashFunction::Poseidon), "4fde9d6f6b2ced3b");
+    }
+}
@@ -0,0 +1,317 @@
+use std::fs;
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::hashing::HashFunction;
+use crate::merkle;
+use crate::result::AnalysisResult;
+use crate::state::state_dir;
+use crate::unix_now;
+
+// Applied in order; PRAGMA user_version records how many have run. Only ever append new entries,
+// never edit one that has shipped.
+const MIGRATIONS: [&str; 8] = ["
+    CREATE TABLE batches (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp INTEGER NOT NULL,
+        top_word TEXT,
+        compressed_payload TEXT NOT NULL
+    );
+    CREATE TABLE keyword_counts (
+        batch_id INTEGER NOT NULL REFERENCES batches(id),
+        keyword TEXT NOT NULL,
+        count INTEGER NOT NULL
+    );
+    CREATE INDEX batches_timestamp ON batches(timestamp);
+    CREATE INDEX keyword_counts_batch ON keyword_counts(batch_id);
+", "
+    ALTER TABLE batches ADD COLUMN url_root TEXT;
+    CREATE TABLE batch_urls (
+        batch_id INTEGER NOT NULL REFERENCES batches(id),
+        url_hash TEXT NOT NULL
+    );
+    CREATE INDEX batch_urls_batch ON batch_urls(batch_id);
+    CREATE INDEX batch_urls_hash ON batch_urls(url_hash);
+", "
+    ALTER TABLE batches ADD COLUMN links_analyzed INTEGER;
+", "
+    ALTER TABLE batches ADD COLUMN hash TEXT;
+", "
+    ALTER TABLE batches ADD COLUMN memo_signature TEXT;
+", "
+    ALTER TABLE batches ADD COLUMN result_pda TEXT;
+", "
+    ALTER TABLE batches ADD COLUMN cluster TEXT;
+", "
+    ALTER TABLE batches ADD COLUMN canonical_payload TEXT;
+"];
+
+const DEFAULT_RECENT_BATCHES: usize = 10;
+const DEFAULT_TOP_DAYS: u64 = 7;
+
+// `results list [N]`, `results show ID` and `results top [DAYS]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultsQuery {
+    List(usize),
+    Show(i64),
+    Top(u64),
+}
+
+impl ResultsQuery {
+    pub fn parse(action: &str, argument: Option<&str>) -> Result<ResultsQuery, Box<dyn std::error::Error>> {
+        Ok(match action {
+            "list" => ResultsQuery::List(argument.map(str::parse).transpose()?.unwrap_or(DEFAULT_RECENT_BATCHES)),
+            "show" => ResultsQuery::Show(argument.ok_or("results show requires a batch id")?.parse()?),
+            "top" => ResultsQuery::Top(argument.map(str::parse).transpose()?.unwrap_or(DEFAULT_TOP_DAYS)),
+            other => return Err(format!("Unknown results action: {} (expected list, show or top)", other).into()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredBatch {
+    pub id: i64,
+    pub timestamp: i64,
+    pub top_word: Option<String>,
+    pub compressed_payload: String,
+}
+
+// A batch with its word counts, highest first
+pub type BatchWords = (StoredBatch, Vec<(String, u32)>);
+
+// What verify-onchain needs of a batch. canonical_payload, the exact string whose digest was
+// submitted, is None for batches recorded before it was stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchAnchor {
+    pub id: i64,
+    pub canonical_payload: Option<String>,
+    pub memo_signature: Option<String>,
+    pub result_pda: Option<String>,
+    pub cluster: Option<String>,
+}
+
+// Over every stored batch; batches recorded before links were counted add none
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchTotals {
+    pub batches: usize,
+    pub links_analyzed: usize,
+    pub first_timestamp: Option<i64>,
+    pub last_timestamp: Option<i64>,
+}
+
+// Every emitted result, kept in `<data dir>/solfhe-analyzer/results.db` across restarts
+pub struct ResultsDb {
+    conn: Connection,
+}
+
+impl ResultsDb {
+    pub fn open_default() -> Result<ResultsDb, Box<dyn std::error::Error>> {
+        let dir = state_dir();
+        fs::create_dir_all(&dir)?;
+        ResultsDb::open(&dir.join("results.db"))
+    }
+
+    pub fn open(path: &Path) -> Result<ResultsDb, Box<dyn std::error::Error>> {
+        let db = ResultsDb { conn: Connection::open(path)? };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    fn migrate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let version: i64 = self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let version = version as usize;
+        if version > MIGRATIONS.len() {
+            return Err(format!("results database schema version {} is newer than this analyzer supports", version).into());
+        }
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            self.conn
+                .execute_batch(&format!("BEGIN; {} PRAGMA user_version = {}; COMMIT;", migration, index + 1))?;
+        }
+        Ok(())
+    }
+
+    // Also keeps the batch's salted URL hashes, so inclusion proofs can be made later, the cluster
+    // its result names and the canonical payload its digest is computed from. Batches recorded
+    // before those were have NULL.
+    pub fn record(&self, result: &AnalysisResult, compressed_payload: &str, canonical_payload: &str, hash: HashFunction, cluster: &str) -> rusqlite::Result<i64> {
+        let url_root = merkle::root(&result.url_hashes, hash).ok();
+        self.conn.execute(
+            "INSERT INTO batches (timestamp, top_word, compressed_payload, url_root, links_analyzed, hash, cluster, canonical_payload) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![result.timestamp, result.most_common_word, compressed_payload, url_root, result.links_analyzed as i64, hash.to_string(), cluster, canonical_payload],
+        )?;
+        let batch_id = self.conn.last_insert_rowid();
+        for url_hash in merkle::leaves(&result.url_hashes) {
+            self.conn.execute("INSERT INTO batch_urls (batch_id, url_hash) VALUES (?1, ?2)", params![batch_id, url_hash])?;
+        }
+        for word in &result.top_words {
+            self.conn.execute(
+                "INSERT INTO keyword_counts (batch_id, keyword, count) VALUES (?1, ?2, ?3)",
+                params![batch_id, word.word, word.count],
+            )?;
+        }
+        Ok(batch_id)
+    }
+
+    // With --submit, once the batch's memo transaction is confirmed
+    pub fn set_memo_signature(&self, id: i64, signature: &str) -> rusqlite::Result<()> {
+        self.conn.execute("UPDATE batches SET memo_signature = ?1 WHERE id = ?2", params![signature, id])?;
+        Ok(())
+    }
+
+    // With --program-id, the address of the batch's account in the result registry program
+    pub fn set_result_pda(&self, id: i64, address: &str) -> rusqlite::Result<()> {
+        self.conn.execute("UPDATE batches SET result_pda = ?1 WHERE id = ?2", params![address, id])?;
+        Ok(())
+    }
+
+    pub fn recent(&self, limit: usize) -> rusqlite::Result<Vec<StoredBatch>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, timestamp, top_word, compressed_payload FROM batches ORDER BY id DESC LIMIT ?1",
+        )?;
+        let batches = stmt.query_map([limit as i64], stored_batch)?.collect();
+        batches
+    }
+
+    pub fn batch(&self, id: i64) -> rusqlite::Result<Option<BatchWords>> {
+        let batch = self.conn
+            .query_row(
+                "SELECT id, timestamp, top_word, compressed_payload FROM batches WHERE id = ?1",
+                [id],
+                stored_batch,
+            )
+            .optional()?;
+        let Some(batch) = batch else {
+            return Ok(None);
+        };
+        let mut stmt = self.conn.prepare(
+            "SELECT keyword, count FROM keyword_counts WHERE batch_id = ?1 ORDER BY count DESC, keyword",
+        )?;
+        let counts = stmt.query_map([id], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<rusqlite::Result<_>>()?;
+        Ok(Some((batch, counts)))
+    }
+
+    // Batches `first` through `last`, either end open when None, oldest first
+    pub fn anchors(&self, first: Option<i64>, last: Option<i64>) -> rusqlite::Result<Vec<BatchAnchor>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, canonical_payload, memo_signature, result_pda, cluster FROM batches WHERE id >= ?1 AND id <= ?2 ORDER BY id",
+        )?;
+        let anchors = stmt
+            .query_map(params![first.unwrap_or(i64::MIN), last.unwrap_or(i64::MAX)], |row| {
+                Ok(BatchAnchor {
+                    id: row.get(0)?,
+                    canonical_payload: row.get(1)?,
+                    memo_signature: row.get(2)?,
+                    result_pda: row.get(3)?,
+                    cluster: row.get(4)?,
+                })
+            })?
+            .collect();
+        anchors
+    }
+
+    pub fn batch_url_root(&self, id: i64) -> rusqlite::Result<Option<String>> {
+        self.conn
+            .query_row("SELECT url_root FROM batches WHERE id = ?1", [id], |row| row.get(0))
+            .optional()
+            .map(Option::flatten)
+    }
+
+    // The hash function of the batch's url_root; batches recorded before it was stored are sha256
+    pub fn batch_hash(&self, id: i64) -> rusqlite::Result<HashFunction> {
+        let hash: Option<String> = self.conn
+            .query_row("SELECT hash FROM batches WHERE id = ?1", [id], |row| row.get(0))
+            .optional()?
+            .flatten();
+        Ok(hash.and_then(|hash| hash.parse().ok()).unwrap_or_default())
+    }
+
+    // None for an unknown batch or one recorded before URL commitments existed
+    pub fn batch_url_hashes(&self, id: i64) -> rusqlite::Result<Option<Vec<String>>> {
+        if self.batch_url_root(id)?.is_none() {
+            return Ok(None);
+        }
+        let mut stmt = self.conn.prepare("SELECT url_hash FROM batch_urls WHERE batch_id = ?1 ORDER BY url_hash")?;
+        let url_hashes = stmt.query_map([id], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        Ok(Some(url_hashes))
+    }
+
+    pub fn latest_batch_with_url(&self, url_hash: &str) -> rusqlite::Result<Option<i64>> {
+        self.conn
+            .query_row("SELECT MAX(batch_id) FROM batch_urls WHERE url_hash = ?1", [url_hash], |row| row.get(0))
+    }
+
+    // Keywords summed over every batch since `since` (unix seconds), highest total first
+    pub fn top_keywords_since(&self, since: i64, limit: usize) -> rusqlite::Result<Vec<(String, u32)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT keyword_counts.keyword, SUM(keyword_counts.count) AS total
+             FROM keyword_counts JOIN batches ON batches.id = keyword_counts.batch_id
+             WHERE batches.timestamp >= ?1
+             GROUP BY keyword_counts.keyword
+             ORDER BY total DESC, keyword_counts.keyword
+             LIMIT ?2",
+        )?;
+        let totals = stmt.query_map(params![since, limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?.collect();
+        totals
+    }
+
+    pub fn totals(&self) -> rusqlite::Result<BatchTotals> {
+        self.conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(links_analyzed), 0), MIN(timestamp), MAX(timestamp) FROM batches",
+            [],
+            |row| {
+                Ok(BatchTotals {
+                    batches: row.get::<_, i64>(0)? as usize,
+                    links_analyzed: row.get::<_, i64>(1)? as usize,
+                    first_timestamp: row.get(2)?,
+                    last_timestamp: row.get(3)?,
+                })
+            },
+        )
+    }
+
+    // Every keyword summed over all batches
+    pub fn keyword_totals(&self) -> rusqlite::Result<Vec<(String, u32)>> {
+        let mut stmt = self.conn.prepare("SELECT keyword, SUM(count) FROM keyword_counts GROUP BY keyword")?;
+        let totals = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect();
+        totals
+    }
+}
+
+fn stored_batch(row: &rusqlite::Row) -> rusqlite::Result<StoredBatch> {
+    Ok(StoredBatch {
+        id: row.get(0)?,
+        timestamp: row.get(1)?,
+        top_word: row.get(2)?,
+        compressed_payload: row.get(3)?,
+    })
+}
+
+// Backs the `results` subcommand; tab separated so the output is easy to cut/sort
+pub fn run_results_query(query: ResultsQuery) -> Result<(), Box<dyn std::error::Error>> {
+    let db = ResultsDb::open_default()?;
+    match query {
+        ResultsQuery::List(limit) => {
+            for batch in db.recent(limit)? {
+                println!("{}\t{}\t{}", batch.id, batch.timestamp, batch.top_word.unwrap_or_default());
+            }
+        }
+        ResultsQuery::Show(id) => {
+            let (batch, counts) = db.batch(id)?.ok_or_else(|| format!("No batch with id {}", id))?;
+            println!("id\t{}", batch.id);
+            println!("timestamp\t{}", batch.timestamp);
+            println!("top_word\t{}", batch.top_word.unwrap_or_default());
+            println!("compressed_payload\t{}", batch.compressed_payload);
+            for (keyword, count) in counts {
+                println!("{}\t{}", keyword, count);
+            }
+        }
+        ResultsQuery::Top(days) => {
+            let since = unix_now() - (days * 24 * 60 * 60) as i64;
+            for (keyword, total) in db.top_keywords_since(since, DEFAULT_RECENT_BATCHES)? {
+                println!("{}\t{}", keyword, total);
+            }
+        }
+    }
+    Ok(())
+}
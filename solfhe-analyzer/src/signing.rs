@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, write_keypair_file, Keypair, Signature, Signer};
+use tracing::info;
+
+use crate::canonical::canonical_json;
+use crate::digest::legacy_canonicalization;
+use crate::error::AnalyzerError;
+use crate::integrity;
+use crate::state::{load_verified_counters, save_verified_counters, state_dir};
+use crate::Config;
+
+// Added to a signed or HMAC-tagged envelope, and therefore never part of the authenticated bytes
+const SIGNATURE_FIELDS: [&str; 3] = ["signature", "public_key", "hmac"];
+
+// Results are signed with --signing-key (a Solana keypair JSON file; both are plain ed25519) or
+// else with a key generated on first use in the state directory
+pub fn load_or_create_signing_key(path: Option<&Path>) -> Result<Keypair, Box<dyn std::error::Error>> {
+    if let Some(path) = path {
+        return read_keypair_file(path).map_err(|e| format!("Failed to read signing key {}: {}", path.display(), e).into());
+    }
+
+    let path = state_dir().join("signing-key.json");
+    if path.exists() {
+        return read_keypair_file(&path).map_err(|e| format!("Failed to read signing key {}: {}", path.display(), e).into());
+    }
+    fs::create_dir_all(state_dir())?;
+    let keypair = Keypair::new();
+    write_keypair_file(&keypair, &path)?;
+    info!("Generated signing key {} at {}", keypair.pubkey(), path.display());
+    Ok(keypair)
+}
+
+// Canonicalization version 1. Objects are rebuilt with their keys in sorted order, so the bytes don't depend on field order
+// or on whether serde_json preserves insertion order. Floats are written in their shortest
+// round-tripping form (serde_json's float_roundtrip feature makes parsing them exact), so a
+// verifier re-serializing a parsed file gets the very same bytes.
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let mut keys: Vec<&String> = fields.keys().collect();
+            keys.sort();
+            Value::Object(keys.into_iter().map(|key| (key.clone(), canonicalize(&fields[key]))).collect::<Map<_, _>>())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
+
+// In the canonicalization the envelope names, like its on-chain payload; version 2 and later are
+// canonical_json
+pub fn canonical_bytes(envelope: &Value) -> Vec<u8> {
+    let mut unsigned = envelope.clone();
+    if let Some(fields) = unsigned.as_object_mut() {
+        for field in SIGNATURE_FIELDS {
+            fields.remove(field);
+        }
+    }
+    let version = envelope.get("canonicalization").and_then(Value::as_u64).unwrap_or(legacy_canonicalization() as u64);
+    if version >= 2 {
+        return canonical_json(&unsigned).into_bytes();
+    }
+    serde_json::to_vec(&canonicalize(&unsigned)).expect("a JSON value always serializes")
+}
+
+pub fn sign(envelope: &mut Value, keypair: &Keypair) {
+    let signature = keypair.sign_message(&canonical_bytes(envelope));
+    if let Some(fields) = envelope.as_object_mut() {
+        fields.insert("signature".to_string(), Value::String(signature.to_string()));
+        fields.insert("public_key".to_string(), Value::String(keypair.pubkey().to_string()));
+    }
+}
+
+// Returns the signer when the signature matches the envelope's content
+pub fn verify(envelope: &Value) -> Result<Pubkey, String> {
+    let field = |name: &str| envelope[name].as_str().ok_or_else(|| format!("no {} field", name));
+    let signature = Signature::from_str(field("signature")?).map_err(|e| format!("invalid signature: {}", e))?;
+    let public_key = Pubkey::from_str(field("public_key")?).map_err(|e| format!("invalid public key: {}", e))?;
+    if !signature.verify(public_key.as_ref(), &canonical_bytes(envelope)) {
+        return Err("signature does not match the content".to_string());
+    }
+    Ok(public_key)
+}
+
+// A single JSON document or every line of a JSON Lines file
+pub fn read_documents(path: &Path) -> Result<Vec<Value>, AnalyzerError> {
+    let contents = fs::read_to_string(path).map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e)))?;
+    match serde_json::from_str(&contents) {
+        Ok(document) => Ok(vec![document]),
+        Err(_) => contents.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(|e| AnalyzerError::Extraction(format!("{}: {}", path.display(), e))),
+    }
+}
+
+// Backs the `verify` subcommand. Takes a single JSON document or a JSON Lines file and checks
+// every result in it, optionally requiring a specific signer (--public-key). With --hmac the
+// results' HMAC tags are checked instead.
+pub fn run_verify(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    if config.verify_hmac {
+        return integrity::run_verify_hmac(config);
+    }
+    let path = config.verify_input.as_ref().ok_or_else(|| AnalyzerError::Usage("verify requires a file path".to_string()))?;
+    let expected = config.verify_public_key.as_deref()
+        .map(Pubkey::from_str)
+        .transpose()
+        .map_err(|e| AnalyzerError::Usage(format!("invalid --public-key: {}", e)))?;
+
+    let mut counters = if config.track_counters { Some(load_verified_counters()?) } else { None };
+    let mut failures = Vec::new();
+    for (index, document) in read_documents(path)?.iter().enumerate() {
+        match verify(document) {
+            Ok(signer) if expected.is_none_or(|expected| expected == signer) => {
+                println!("result {}: valid signature by {}", index + 1, signer);
+                if let Some(counters) = &mut counters {
+                    if let Err(e) = check_counter(counters, &signer, document) {
+                        failures.push(format!("result {}: {}", index + 1, e));
+                    }
+                }
+            }
+            Ok(signer) => failures.push(format!("result {}: signed by {}, not the expected key", index + 1, signer)),
+            Err(e) => failures.push(format!("result {}: {}", index + 1, e)),
+        }
+    }
+    if let Some(counters) = &counters {
+        save_verified_counters(counters)?;
+    }
+    if failures.is_empty() {
+        return Ok(());
+    }
+    Err(AnalyzerError::InvalidSignature(failures.join("; ")).into())
+}
+
+// A signed result must carry a higher batch counter than any result verified before from the
+// same key; an equal or lower one is a replay. A result marked counter_reset is let through and
+// reported, since the signer lost its counter (or is new) and starts again from 1. Results from
+// before the counter existed (counter 0) are not tracked.
+fn check_counter(highest: &mut BTreeMap<String, u64>, signer: &Pubkey, envelope: &Value) -> Result<(), String> {
+    let counter = envelope["counter"].as_u64().unwrap_or(0);
+    if counter == 0 {
+        return Ok(());
+    }
+    let seen = highest.entry(signer.to_string()).or_insert(0);
+    if envelope["counter_reset"].as_bool().unwrap_or(false) {
+        if *seen > 0 {
+            println!("  counter of {} was reset: {} after {}", signer, counter, seen);
+        }
+    } else if counter <= *seen {
+        return Err(format!("batch counter {} does not exceed {} already verified from {} (replayed result?)", counter, seen, signer));
+    }
+    *seen = counter;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tampered_payload_is_rejected() {
+        let keypair = Keypair::new();
+        let mut envelope = json!({
+            "schema_version": 6,
+            "result": { "most_common_word": "solana", "count": 3, "top_words_tfidf": [{ "word": "swap", "score": 0.1 }] }
+        });
+        sign(&mut envelope, &keypair);
+
+        // Verification works on what a consumer reads back, not just on the in-memory value
+        let written: Value = serde_json::from_str(&serde_json::to_string_pretty(&envelope).unwrap()).unwrap();
+        assert_eq!(verify(&written), Ok(keypair.pubkey()));
+
+        let mut tampered = written.clone();
+        tampered["result"]["count"] = json!(4);
+        assert_eq!(verify(&tampered), Err("signature does not match the content".to_string()));
+
+        let mut resigned = written;
+        resigned["public_key"] = json!(Keypair::new().pubkey().to_string());
+        assert!(verify(&resigned).is_err());
+    }
+
+    #[test]
+    fn replayed_counters_are_flagged() {
+        let signer = Keypair::new().pubkey();
+        let mut highest = BTreeMap::new();
+        let envelope = |counter: u64, reset: bool| json!({ "counter": counter, "counter_reset": reset, "nonce": "00" });
+
+        assert_eq!(check_counter(&mut highest, &signer, &envelope(1, true)), Ok(()));
+        assert_eq!(check_counter(&mut highest, &signer, &envelope(2, false)), Ok(()));
+        assert!(check_counter(&mut highest, &signer, &envelope(2, false)).is_err());
+        assert!(check_counter(&mut highest, &signer, &envelope(1, false)).is_err());
+        // Another signer counts on its own, and results from before the counter aren't tracked
+        assert_eq!(check_counter(&mut highest, &Keypair::new().pubkey(), &envelope(1, false)), Ok(()));
+        assert_eq!(check_counter(&mut highest, &signer, &json!({})), Ok(()));
+        // A lost counter file starts over visibly
+        assert_eq!(check_counter(&mut highest, &signer, &envelope(1, true)), Ok(()));
+        assert_eq!(highest[&signer.to_string()], 1);
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::result::PairCount;
+
+// Bump whenever the shape of PersistedState changes incompatibly; older files are discarded on
+// load. A new field with a default doesn't need a bump.
+pub const STATE_VERSION: u32 = 1;
+
+// What survives a restart: digests of already analyzed URLs (oldest first), the
+// last_visit_time cursor used by session mode and the network co-occurrence counts.
+// --reset-state forgets all of it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PersistedState {
+    pub version: u32,
+    pub seen: Vec<u64>,
+    pub visit_cursor: Option<i64>,
+    #[serde(default)]
+    pub cooccurrence: Vec<PairCount>,
+}
+
+pub fn state_dir() -> PathBuf {
+    dirs::data_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default()
+        .join("solfhe-analyzer")
+}
+
+fn state_path() -> PathBuf {
+    state_dir().join("state.json")
+}
+
+// Per-installation salt for URL hashing, generated on first use so hashes from different
+// machines can't be correlated
+pub fn load_or_create_salt() -> Result<String, Box<dyn std::error::Error>> {
+    load_or_create_random("salt", 32)
+}
+
+// Anonymous ID sent with --share-aggregates; unrelated to the salt
+pub fn load_or_create_client_id() -> Result<String, Box<dyn std::error::Error>> {
+    load_or_create_random("client-id", 16)
+}
+
+fn load_or_create_random(name: &str, len: usize) -> Result<String, Box<dyn std::error::Error>> {
+    let path = state_dir().join(name);
+    match fs::read_to_string(&path) {
+        Ok(value) if !value.trim().is_empty() => return Ok(value.trim().to_string()),
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    fs::create_dir_all(state_dir())?;
+    let value = hex::encode((0..len).map(|_| rand::random::<u8>()).collect::<Vec<u8>>());
+    fs::write(&path, &value)?;
+    Ok(value)
+}
+
+// Every envelope this installation writes carries the next value of a counter kept in
+// batch-counter, next to a random nonce, so a verifier can tell a replayed result from a fresh
+// one. The counter is never part of PersistedState and survives --reset-state. `reset` is set
+// when the file was missing or unreadable and the count starts over at 1, which includes the
+// very first result of an installation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchCounter {
+    pub value: u64,
+    pub reset: bool,
+}
+
+// The new value is on disk before it is handed out, so a crash can skip a value but never
+// reuse one
+pub fn next_batch_counter() -> Result<BatchCounter, Box<dyn std::error::Error>> {
+    let path = state_dir().join("batch-counter");
+    let previous = match fs::read_to_string(&path) {
+        Ok(contents) => match contents.trim().parse::<u64>() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("batch counter {} is corrupted ({}), starting over", path.display(), e);
+                None
+            }
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e).into()),
+    };
+
+    let counter = BatchCounter { value: previous.map_or(1, |value| value + 1), reset: previous.is_none() };
+    fs::create_dir_all(state_dir())?;
+    write_atomically(&path, counter.value.to_string().as_bytes())?;
+    Ok(counter)
+}
+
+// A random 128-bit nonce (hex) for an envelope
+pub fn envelope_nonce() -> String {
+    hex::encode(rand::random::<[u8; 16]>())
+}
+
+// With verify --track-counters: the highest counter verified so far per signer (base58)
+fn verified_counters_path() -> PathBuf {
+    state_dir().join("verified-counters.json")
+}
+
+pub fn load_verified_counters() -> Result<BTreeMap<String, u64>, Box<dyn std::error::Error>> {
+    match fs::read_to_string(verified_counters_path()) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save_verified_counters(counters: &BTreeMap<String, u64>) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(state_dir())?;
+    write_atomically(&verified_counters_path(), &serde_json::to_vec_pretty(counters)?)
+}
+
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, contents)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+impl PersistedState {
+    // A missing file is a normal first run; unreadable or outdated files are reported and ignored
+    pub fn load() -> Option<PersistedState> {
+        let path = state_path();
+        let contents = fs::read_to_string(&path).ok()?;
+
+        match serde_json::from_str::<PersistedState>(&contents) {
+            Ok(state) if state.version == STATE_VERSION => Some(state),
+            Ok(state) => {
+                warn!(
+                    "discarding state file {} with unsupported version {} (expected {})",
+                    path.display(),
+                    state.version,
+                    STATE_VERSION
+                );
+                None
+            }
+            Err(e) => {
+                warn!("discarding corrupted state file {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    // Written to a temporary file first so a crash mid-write never leaves a torn state file
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(state_dir())?;
+        let path = state_path();
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec(self)?)?;
+        fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+
+    pub fn reset() -> std::io::Result<()> {
+        match fs::remove_file(state_path()) {
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
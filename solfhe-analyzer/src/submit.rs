@@ -0,0 +1,348 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use solana_client::client_error::ClientError;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::Message;
+use solana_sdk::native_token::{lamports_to_sol, LAMPORTS_PER_SOL};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use tracing::{info, warn};
+
+use crate::registry::RESULT_ACCOUNT_SIZE;
+use crate::Config;
+
+// Where --submit anchors results. Solana is the only chain so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitTarget {
+    Solana,
+}
+
+impl std::str::FromStr for SubmitTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "solana" => Ok(SubmitTarget::Solana),
+            other => Err(format!("Unknown submit target: {} (expected solana)", other)),
+        }
+    }
+}
+
+// The Solana cluster results go to, named in every envelope and in the results database so a
+// devnet attestation can't be taken for a mainnet one. Localnet, the validator of the demo, is
+// the default: nothing is sent to a public cluster unless asked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Cluster {
+    Devnet,
+    Testnet,
+    MainnetBeta,
+    #[default]
+    Localnet,
+}
+
+impl Cluster {
+    // Devnet and localnet have a faucet that can top up the payer; testnet's is too rate limited
+    // to rely on, and mainnet SOL is real money
+    pub fn airdrops(self) -> bool {
+        matches!(self, Cluster::Devnet | Cluster::Localnet)
+    }
+
+    pub fn rpc_url(self) -> &'static str {
+        match self {
+            Cluster::Devnet => "https://api.devnet.solana.com",
+            Cluster::Testnet => "https://api.testnet.solana.com",
+            Cluster::MainnetBeta => "https://api.mainnet-beta.solana.com",
+            Cluster::Localnet => "http://localhost:8899",
+        }
+    }
+}
+
+impl std::str::FromStr for Cluster {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "devnet" => Ok(Cluster::Devnet),
+            "testnet" => Ok(Cluster::Testnet),
+            "mainnet-beta" => Ok(Cluster::MainnetBeta),
+            "localnet" => Ok(Cluster::Localnet),
+            other => Err(format!("Unknown cluster: {} (expected devnet, testnet, mainnet-beta or localnet)", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for Cluster {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Cluster::Devnet => "devnet",
+            Cluster::Testnet => "testnet",
+            Cluster::MainnetBeta => "mainnet-beta",
+            Cluster::Localnet => "localnet",
+        })
+    }
+}
+
+// Only the three levels current nodes understand, not the deprecated aliases CommitmentLevel
+// also parses
+pub fn parse_commitment(s: &str) -> Result<CommitmentConfig, String> {
+    let commitment = match s {
+        "processed" => CommitmentLevel::Processed,
+        "confirmed" => CommitmentLevel::Confirmed,
+        "finalized" => CommitmentLevel::Finalized,
+        other => return Err(format!("Unknown commitment: {} (expected processed, confirmed or finalized)", other)),
+    };
+    Ok(CommitmentConfig { commitment })
+}
+
+pub const DEFAULT_RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Run before anything is sent, so a wrong --rpc-url or a node that is down fails at startup with
+// the endpoint in the message instead of in the middle of the first emit
+pub async fn preflight(client: &RpcClient, cluster: &str) -> Result<(), String> {
+    let unreachable = |e: ClientError| format!("the {} RPC endpoint {} is unreachable or unhealthy: {}", cluster, client.url(), e);
+    client.get_health().await.map_err(unreachable)?;
+    let version = client.get_version().await.map_err(unreachable)?;
+    info!("Connected to {} at {} (solana-core {})", cluster, client.url(), version.solana_core);
+    Ok(())
+}
+
+// What a submission pays per compute unit on top of the base fee, in micro-lamports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriorityFee {
+    Fixed(u64),
+    // The median of the fees paid over the recent slots (up to 150) by transactions writing the
+    // same accounts
+    Auto,
+}
+
+impl std::str::FromStr for PriorityFee {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(PriorityFee::Auto),
+            micro_lamports => micro_lamports
+                .parse()
+                .map(PriorityFee::Fixed)
+                .map_err(|_| format!("Invalid priority fee: {} (expected micro-lamports per compute unit or auto)", s)),
+        }
+    }
+}
+
+fn median_fee(mut fees: Vec<u64>) -> u64 {
+    fees.sort_unstable();
+    fees.get(fees.len() / 2).copied().unwrap_or(0)
+}
+
+// How the memo and the registry transactions are built and sent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubmitOptions {
+    pub priority_fee: Option<PriorityFee>,
+}
+
+impl SubmitOptions {
+    // Prepends the compute budget instruction that sets the priority fee, if any. A fee of zero,
+    // which is what `auto` comes to on an idle cluster, adds nothing.
+    pub async fn budget(&self, client: &RpcClient, payer: &Pubkey, instructions: &[Instruction]) -> Result<Vec<Instruction>, ClientError> {
+        let price = match self.priority_fee {
+            None => 0,
+            Some(PriorityFee::Fixed(micro_lamports)) => micro_lamports,
+            Some(PriorityFee::Auto) => {
+                let mut writable = vec![*payer];
+                writable.extend(instructions.iter().flat_map(|instruction| &instruction.accounts).filter(|account| account.is_writable).map(|account| account.pubkey));
+                let recent = client.get_recent_prioritization_fees(&writable).await?;
+                median_fee(recent.iter().map(|fee| fee.prioritization_fee).collect())
+            }
+        };
+        let mut budgeted = Vec::with_capacity(instructions.len() + 1);
+        if price > 0 {
+            budgeted.push(ComputeBudgetInstruction::set_compute_unit_price(price));
+        }
+        budgeted.extend_from_slice(instructions);
+        Ok(budgeted)
+    }
+}
+
+const BLOCKHASH_ATTEMPTS: u32 = 3;
+
+// Signs and sends `instructions` (see SubmitOptions::budget) once they simulate cleanly, so a
+// transaction that would fail costs nothing and its program logs end up in the error. A
+// blockhash that expired before the transaction landed is replaced and the transaction signed
+// again, BLOCKHASH_ATTEMPTS times in all.
+pub async fn send(client: &RpcClient, payer: &Keypair, instructions: &[Instruction]) -> Result<Signature, Box<dyn std::error::Error>> {
+    for attempt in 1..=BLOCKHASH_ATTEMPTS {
+        let blockhash = client.get_latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(instructions, Some(&payer.pubkey()), &[payer], blockhash);
+        let simulation = client.simulate_transaction(&transaction).await?.value;
+        match simulation.err {
+            Some(TransactionError::BlockhashNotFound) => {}
+            Some(e) => {
+                let logs = simulation.logs.unwrap_or_default();
+                return Err(format!("the transaction failed in simulation: {}\n  {}", e, logs.join("\n  ")).into());
+            }
+            None => match client.send_and_confirm_transaction(&transaction).await {
+                Err(e) if e.get_transaction_error() == Some(TransactionError::BlockhashNotFound) => {}
+                sent => return Ok(sent?),
+            },
+        }
+        warn!("Blockhash {} expired, signing again with a new one ({}/{})", blockhash, attempt, BLOCKHASH_ATTEMPTS);
+    }
+    Err(format!("the blockhash expired {} times in a row", BLOCKHASH_ATTEMPTS).into())
+}
+
+const SUBMIT_ATTEMPTS: u32 = 5;
+const SUBMIT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+// The payer and memo signer: --keypair (or `keypair`), else the keypair file named by
+// SOLANA_KEYPAIR. Unlike the watch-mode demo it is never generated or airdropped to.
+pub fn load_submit_keypair(path: Option<&Path>) -> Result<Keypair, Box<dyn std::error::Error>> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => std::env::var_os("SOLANA_KEYPAIR")
+            .map(Into::into)
+            .ok_or("--submit solana needs a keypair: --keypair FILE or SOLANA_KEYPAIR")?,
+    };
+    read_keypair_file(&path).map_err(|e| format!("Failed to read keypair {}: {}", path.display(), e).into())
+}
+
+// What goes on-chain for a result: `solfhe:v<schema_version>:<base58 SHA-256 of the canonical
+// payload>`. Only the digest is anchored, so the memo stays small whatever the result's size, and
+// anyone holding the result can recompute it.
+pub fn memo_text(schema_version: u32, payload_sha256: &str) -> Result<String, hex::FromHexError> {
+    Ok(format!("solfhe:v{}:{}", schema_version, bs58::encode(hex::decode(payload_sha256)?).into_string()))
+}
+
+// The hex SHA-256 anchored by a memo_text memo, None for any other memo
+pub fn memo_digest(memo: &str) -> Option<String> {
+    let (schema_version, digest) = memo.strip_prefix("solfhe:v")?.split_once(':')?;
+    schema_version.parse::<u32>().ok()?;
+    Some(hex::encode(bs58::decode(digest).into_vec().ok()?))
+}
+
+const AIRDROP_LAMPORTS: u64 = LAMPORTS_PER_SOL;
+const AIRDROP_TIMEOUT: Duration = Duration::from_secs(60);
+
+// What one submission costs the payer: the memo transaction's fee, priority fee included, and,
+// with a registry program, the rent of the new result account and the fee of its transaction
+// too. Fees are per signature, so the memo's content doesn't change them.
+pub async fn submission_cost(client: &RpcClient, payer: &Pubkey, registry: bool, options: &SubmitOptions) -> Result<u64, ClientError> {
+    let instructions = options.budget(client, payer, &[spl_memo::build_memo(b"solfhe", &[payer])]).await?;
+    let blockhash = client.get_latest_blockhash().await?;
+    let fee = client.get_fee_for_message(&Message::new_with_blockhash(&instructions, Some(payer), &blockhash)).await?;
+    if !registry {
+        return Ok(fee);
+    }
+    Ok(2 * fee + client.get_minimum_balance_for_rent_exemption(RESULT_ACCOUNT_SIZE).await?)
+}
+
+// Makes sure `payer` holds at least `required` lamports, at startup and before every
+// submission, so a submission doesn't fail on fees after a long analysis. A cluster with a faucet
+// (see Cluster::airdrops) is asked for an airdrop, which is waited for; elsewhere, including an
+// --rpc-url of unknown cluster, the error names the address and the amount to send it.
+pub async fn ensure_balance(client: &RpcClient, payer: &Pubkey, cluster: Option<Cluster>, required: u64) -> Result<u64, Box<dyn std::error::Error>> {
+    let balance = client.get_balance(payer).await?;
+    if balance >= required {
+        return Ok(balance);
+    }
+    let Some(cluster) = cluster.filter(|cluster| cluster.airdrops()) else {
+        return Err(format!(
+            "{} holds {} SOL but submitting a result needs at least {} SOL; send it {} SOL or more before submitting",
+            payer,
+            lamports_to_sol(balance),
+            lamports_to_sol(required),
+            lamports_to_sol(required - balance)
+        )
+        .into());
+    };
+
+    let amount = AIRDROP_LAMPORTS.max(required - balance);
+    info!("{} holds {} SOL, requesting an airdrop of {} SOL on {}", payer, lamports_to_sol(balance), lamports_to_sol(amount), cluster);
+    let signature = client.request_airdrop(payer, amount).await.map_err(|e| format!("the {} airdrop to {} failed: {}", cluster, payer, e))?;
+    let started = Instant::now();
+    while !client.confirm_transaction(&signature).await? {
+        if started.elapsed() > AIRDROP_TIMEOUT {
+            return Err(format!("the {} airdrop to {} was not confirmed within {:?}", cluster, payer, AIRDROP_TIMEOUT).into());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    Ok(client.get_balance(payer).await?)
+}
+
+// Backs `wallet status`: the --keypair (or SOLANA_KEYPAIR) address, its balance and the cluster
+pub fn run_wallet_status(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let address = load_submit_keypair(config.keypair.as_deref())?.pubkey();
+    let client = config.rpc_client();
+    let balance = crate::native::current_thread_runtime()?.block_on(async {
+        preflight(&client, &config.cluster_name()).await?;
+        client.get_balance(&address).await.map_err(|e| e.to_string())
+    })?;
+    println!("address: {}", address);
+    println!("balance: {} SOL ({} lamports)", lamports_to_sol(balance), balance);
+    println!("cluster: {} ({})", config.cluster_name(), client.url());
+    Ok(())
+}
+
+async fn send_memo(client: &RpcClient, payer: &Keypair, memo: &str, options: &SubmitOptions) -> Result<Signature, Box<dyn std::error::Error>> {
+    let instructions = options.budget(client, &payer.pubkey(), &[spl_memo::build_memo(memo.as_bytes(), &[&payer.pubkey()])]).await?;
+    send(client, payer, &instructions).await
+}
+
+// A single Memo instruction signed by the payer. Failures (an RPC node that is down, a payer
+// short of funds for the fee) are retried SUBMIT_ATTEMPTS times in all, waiting 1s, 2s, 4s and
+// 8s in between; the last error is returned and the caller keeps the result locally.
+pub async fn submit_memo(client: &RpcClient, payer: &Keypair, memo: &str, options: &SubmitOptions) -> Result<Signature, Box<dyn std::error::Error>> {
+    let mut delay = SUBMIT_RETRY_DELAY;
+    for attempt in 1..SUBMIT_ATTEMPTS {
+        match send_memo(client, payer, memo, options).await {
+            Ok(signature) => return Ok(signature),
+            Err(e) => {
+                warn!("Submitting the memo failed ({}), attempt {}/{}, retrying in {:?}", e, attempt, SUBMIT_ATTEMPTS, delay);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+    }
+    send_memo(client, payer, memo, options)
+        .await
+        .map_err(|e| format!("submitting the memo failed after {} attempts: {}", SUBMIT_ATTEMPTS, e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memo_carries_the_schema_and_the_base58_digest() {
+        let sha256 = "00".repeat(31) + "01";
+        assert_eq!(memo_text(20, &sha256).unwrap(), "solfhe:v20:11111111111111111111111111111112");
+        assert!(memo_text(20, "not hex").is_err());
+        assert_eq!(memo_digest(&memo_text(20, &sha256).unwrap()), Some(sha256));
+        assert_eq!(memo_digest("gm"), None);
+        assert_eq!("solana".parse(), Ok(SubmitTarget::Solana));
+        assert!("ethereum".parse::<SubmitTarget>().is_err());
+    }
+
+    #[test]
+    fn cluster_names_round_trip() {
+        for cluster in [Cluster::Devnet, Cluster::Testnet, Cluster::MainnetBeta, Cluster::Localnet] {
+            assert_eq!(cluster.to_string().parse(), Ok(cluster));
+        }
+        assert!("mainnet".parse::<Cluster>().is_err());
+        assert_eq!(parse_commitment("confirmed"), Ok(CommitmentConfig::confirmed()));
+        assert!(parse_commitment("max").is_err());
+    }
+
+    #[test]
+    fn priority_fee_is_fixed_or_the_recent_median() {
+        assert_eq!("2500".parse(), Ok(PriorityFee::Fixed(2500)));
+        assert_eq!("auto".parse(), Ok(PriorityFee::Auto));
+        assert!("fast".parse::<PriorityFee>().is_err());
+        assert_eq!(median_fee(vec![0, 300, 10, 0, 5000]), 10);
+        assert_eq!(median_fee(Vec::new()), 0);
+    }
+}
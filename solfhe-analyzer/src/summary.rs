@@ -0,0 +1,155 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use crate::analysis::Keywords;
+use crate::pretty::local_time;
+use crate::result::AnalysisResult;
+use crate::results_db::ResultsDb;
+use crate::Config;
+
+const TOP_WORDS: usize = 10;
+const BAR_WIDTH: usize = 30;
+// The partial blocks ending a bar, one to seven eighths of a cell
+const BAR_EIGHTHS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+// Totals over every result of a run: what `--profile-report` prints when the analyzer stops, and
+// what `report` rebuilds from the results database
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    pub batches: usize,
+    pub links_analyzed: usize,
+    pub word_counts: HashMap<String, u32>,
+    pub category_counts: BTreeMap<String, u32>,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+}
+
+impl Summary {
+    pub fn add(&mut self, result: &AnalysisResult) {
+        self.batches += 1;
+        self.links_analyzed += result.links_analyzed;
+        for (word, &count) in &result.word_counts {
+            *self.word_counts.entry(word.clone()).or_insert(0) += count;
+        }
+        for (category, &count) in &result.category_counts {
+            *self.category_counts.entry(category.clone()).or_insert(0) += count;
+        }
+        let start = result.window.as_ref().map(|window| window.start)
+            .or(result.session.as_ref().map(|session| session.start))
+            .unwrap_or(result.timestamp);
+        self.start = Some(self.start.map_or(start, |first| first.min(start)));
+        self.end = Some(self.end.map_or(result.timestamp, |last| last.max(result.timestamp)));
+    }
+
+    // The database keeps only each batch's top words, so these totals leave out rarer words
+    pub fn from_db(db: &ResultsDb, keywords: &Keywords) -> rusqlite::Result<Summary> {
+        let totals = db.totals()?;
+        let word_counts: HashMap<String, u32> = db.keyword_totals()?.into_iter().collect();
+        let mut category_counts = BTreeMap::new();
+        for (word, &count) in &word_counts {
+            *category_counts.entry(keywords.category(word).name().to_string()).or_insert(0) += count;
+        }
+        Ok(Summary {
+            batches: totals.batches,
+            links_analyzed: totals.links_analyzed,
+            word_counts,
+            category_counts,
+            start: totals.first_timestamp,
+            end: totals.last_timestamp,
+        })
+    }
+
+    // Highest counts first, ties alphabetical
+    fn top_words(&self) -> Vec<(&str, u32)> {
+        let mut words: Vec<(&str, u32)> = self.word_counts.iter().map(|(word, &count)| (word.as_str(), count)).collect();
+        words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        words.truncate(TOP_WORDS);
+        words
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Session summary");
+        let span = match (self.start, self.end) {
+            (Some(start), Some(end)) => format!("{} → {} ({})", local_time(start), local_time(end), duration(end - start)),
+            _ => "nothing emitted".to_string(),
+        };
+        let _ = writeln!(out, "  {:<16}{}", "Time span", span);
+        let _ = writeln!(out, "  {:<16}{}", "Batches", self.batches);
+        let _ = writeln!(out, "  {:<16}{}", "Links analyzed", self.links_analyzed);
+        let _ = writeln!(out, "  {:<16}{}", "Distinct words", self.word_counts.len());
+
+        let _ = writeln!(out, "\nTop words");
+        chart(&mut out, &self.top_words());
+
+        let _ = writeln!(out, "\nCategories");
+        let categories: Vec<(&str, u32)> = self.category_counts.iter().map(|(category, &count)| (category.as_str(), count)).collect();
+        chart(&mut out, &categories);
+        out
+    }
+}
+
+fn chart(out: &mut String, rows: &[(&str, u32)]) {
+    if rows.is_empty() {
+        let _ = writeln!(out, "  (none)");
+        return;
+    }
+    let name_width = rows.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+    let max = rows.iter().map(|&(_, count)| count).max().unwrap_or(0);
+    for &(name, count) in rows {
+        let _ = writeln!(out, "  {:<name_width$}  {:>6}  {}", name, count, bar(count, max));
+    }
+}
+
+// `count` out of `max` as up to BAR_WIDTH cells, to an eighth of a cell; any count above zero
+// gets at least a sliver
+fn bar(count: u32, max: u32) -> String {
+    let eighths = (count as usize * BAR_WIDTH * 8).div_ceil(max.max(1) as usize);
+    let mut bar = "█".repeat(eighths / 8);
+    if !eighths.is_multiple_of(8) {
+        bar.push(BAR_EIGHTHS[eighths % 8 - 1]);
+    }
+    bar
+}
+
+fn duration(secs: i64) -> String {
+    let minutes = secs.max(0) / 60;
+    match (minutes / (24 * 60), minutes / 60 % 24, minutes % 60) {
+        (0, 0, minutes) => format!("{}m", minutes),
+        (0, hours, minutes) => format!("{}h {:02}m", hours, minutes),
+        (days, hours, _) => format!("{}d {}h", days, hours),
+    }
+}
+
+// Backs `report`: the summary of everything in the results database
+pub fn run_report(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let db = ResultsDb::open_default()?;
+    print!("{}", Summary::from_db(&db, &config.rules.keywords)?.render());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bars_are_proportional_to_the_largest_count() {
+        assert_eq!(bar(4, 4).chars().count(), BAR_WIDTH);
+        assert_eq!(bar(2, 4), "█".repeat(BAR_WIDTH / 2));
+        assert_eq!(bar(1, 16), "█▉");
+        assert_eq!(bar(0, 4), "");
+
+        let summary = Summary {
+            batches: 2,
+            links_analyzed: 7,
+            word_counts: HashMap::from([("solana".to_string(), 4), ("swap".to_string(), 2)]),
+            category_counts: BTreeMap::from([("network".to_string(), 4), ("word".to_string(), 2)]),
+            start: Some(1_700_000_000),
+            end: Some(1_700_009_000),
+        };
+        let text = summary.render();
+        assert!(text.contains("(2h 30m)"));
+        assert!(text.contains("Distinct words  2"));
+        assert!(text.contains(&format!("solana       4  {}", "█".repeat(BAR_WIDTH))));
+    }
+}
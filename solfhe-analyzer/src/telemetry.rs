@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use solana_sdk::signature::Keypair;
+
+use crate::result::AnalysisResult;
+use crate::{signing, state};
+
+// Bumped whenever the shape of AggregatePayload changes
+pub const AGGREGATES_VERSION: u32 = 1;
+
+// What `--share-aggregates` uploads: per-network and per-category counts and nothing else. Words,
+// domains and URLs never leave the machine, and the client ID is random rather than derived from
+// the URL hashing salt, so uploads can't be joined with hashed URLs.
+#[derive(Debug, Serialize)]
+pub struct AggregatePayload {
+    pub schema_version: u32,
+    pub client_id: String,
+    pub generated_at: String,
+    pub links_analyzed: usize,
+    pub networks: BTreeMap<String, u32>,
+    pub categories: BTreeMap<String, u32>,
+}
+
+impl AggregatePayload {
+    pub fn new(analysis: &AnalysisResult, networks: &[String], client_id: &str) -> AggregatePayload {
+        AggregatePayload {
+            schema_version: AGGREGATES_VERSION,
+            client_id: client_id.to_string(),
+            generated_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            links_analyzed: analysis.links_analyzed,
+            networks: analysis.word_counts.iter()
+                .filter(|(word, _)| networks.contains(word))
+                .map(|(network, &count)| (network.clone(), count))
+                .collect(),
+            categories: analysis.category_counts.clone(),
+        }
+    }
+}
+
+pub struct AggregateSharer {
+    url: String,
+    client: reqwest::Client,
+    client_id: String,
+}
+
+impl AggregateSharer {
+    pub fn new(url: String, timeout: Duration) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(AggregateSharer { url, client, client_id: state::load_or_create_client_id()? })
+    }
+
+    // Signed only with an explicitly configured key: the generated one would add a stable
+    // identifier the random client ID is meant to avoid
+    pub async fn share(&self, analysis: &AnalysisResult, networks: &[String], signing_key: Option<&Keypair>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut payload = serde_json::to_value(AggregatePayload::new(analysis, networks, &self.client_id))?;
+        if let Some(signing_key) = signing_key {
+            signing::sign(&mut payload, signing_key);
+        }
+        self.post(&payload).await
+    }
+
+    async fn post(&self, payload: &Value) -> Result<(), Box<dyn std::error::Error>> {
+        let response = self.client.post(&self.url).json(payload).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("aggregate collector {} responded with {}", self.url, status).into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_holds_only_network_and_category_counts() {
+        let analysis: AnalysisResult = serde_json::from_value(serde_json::json!({
+            "count": 3,
+            "timestamp": 0,
+            "links_analyzed": 4,
+            "top_words": [],
+            "word_counts": { "solana": 3, "staking": 2, "alice": 1 },
+            "category_counts": { "network": 3, "word": 3 },
+        }))
+        .unwrap();
+        let payload = serde_json::to_value(AggregatePayload::new(&analysis, &["solana".to_string()], "abc")).unwrap();
+
+        let mut fields: Vec<&String> = payload.as_object().unwrap().keys().collect();
+        fields.sort();
+        assert_eq!(fields, ["categories", "client_id", "generated_at", "links_analyzed", "networks", "schema_version"]);
+        assert_eq!(payload["networks"], serde_json::json!({ "solana": 3 }));
+        assert!(!payload.to_string().contains("staking") && !payload.to_string().contains("alice"));
+    }
+}
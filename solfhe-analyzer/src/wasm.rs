@@ -0,0 +1,18 @@
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::analysis::{self, Rules};
+
+// As many top words as a streamed result carries
+const TOP_WORDS: usize = 10;
+
+// Entry point of the `wasm` build for the browser extension: analyzes a list of URLs with the
+// default rules and returns analysis::UrlAnalysis as a plain object. Maps become objects rather
+// than JS Maps so the result reads like the CLI's JSON.
+#[wasm_bindgen]
+pub fn analyze_urls(urls: Vec<String>) -> Result<JsValue, JsError> {
+    let analysis = analysis::analyze_urls(&urls, &Rules::default(), TOP_WORDS);
+    analysis
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
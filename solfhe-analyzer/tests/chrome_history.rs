@@ -0,0 +1,79 @@
+use rusqlite::{params, Connection};
+use solfhe_analyzer::{check_history_schema, open_history, query_recent_urls, AnalyzerError};
+
+// The subset of Chrome's `urls` table the analyzer reads, with Chrome's column definitions
+const URLS_SCHEMA: &str = "CREATE TABLE urls(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url LONGVARCHAR,
+    title LONGVARCHAR,
+    visit_count INTEGER DEFAULT 0 NOT NULL,
+    typed_count INTEGER DEFAULT 0 NOT NULL,
+    last_visit_time INTEGER NOT NULL,
+    hidden INTEGER DEFAULT 0 NOT NULL
+)";
+
+// (url, last_visit_time in Chrome microseconds), deliberately inserted out of visit order
+const ROWS: [(&str, i64); 4] = [
+    ("https://solana.com/docs", 13_350_000_000_000_000),
+    ("https://ethereum.org/en/", 13_350_000_300_000_000),
+    ("https://github.com/solana-labs/solana", 13_350_000_100_000_000),
+    ("https://polkadot.network/", 13_350_000_200_000_000),
+];
+
+fn synthetic_history(conn: &Connection) {
+    conn.execute(URLS_SCHEMA, []).unwrap();
+    for (url, last_visit_time) in ROWS {
+        conn.execute("INSERT INTO urls (url, title, last_visit_time) VALUES (?1, '', ?2)", params![url, last_visit_time])
+            .unwrap();
+    }
+}
+
+#[test]
+fn recent_urls_are_newest_first_and_limited() {
+    let conn = Connection::open_in_memory().unwrap();
+    synthetic_history(&conn);
+
+    let urls = query_recent_urls(&conn, 3).unwrap();
+    assert_eq!(urls, ["https://ethereum.org/en/", "https://polkadot.network/", "https://github.com/solana-labs/solana"]);
+}
+
+#[test]
+fn zero_history_limit_returns_every_url() {
+    let conn = Connection::open_in_memory().unwrap();
+    synthetic_history(&conn);
+
+    assert_eq!(query_recent_urls(&conn, 0).unwrap().len(), ROWS.len());
+}
+
+#[test]
+fn history_file_is_opened_read_only_in_place() {
+    let path = std::env::temp_dir().join(format!("solfhe-history-test-{}", std::process::id()));
+    synthetic_history(&Connection::open(&path).unwrap());
+
+    let conn = open_history(&path).unwrap();
+    assert_eq!(query_recent_urls(&conn, 1).unwrap(), ["https://ethereum.org/en/"]);
+    assert!(conn.execute("DELETE FROM urls", []).is_err(), "history must never be writable");
+
+    drop(conn);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn unexpected_schema_is_reported_per_browser() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute("CREATE TABLE visits(id INTEGER PRIMARY KEY)", []).unwrap();
+    assert_eq!(
+        check_history_schema(&conn, "chrome"),
+        Err(AnalyzerError::SchemaMismatch { browser: "chrome".to_string(), detail: "no urls table".to_string() })
+    );
+
+    conn.execute("CREATE TABLE urls(id INTEGER PRIMARY KEY, url LONGVARCHAR)", []).unwrap();
+    let Err(AnalyzerError::SchemaMismatch { detail, .. }) = check_history_schema(&conn, "chrome") else {
+        panic!("urls without last_visit_time must not pass the schema check");
+    };
+    assert!(detail.contains("last_visit_time"));
+
+    let conn = Connection::open_in_memory().unwrap();
+    synthetic_history(&conn);
+    assert_eq!(check_history_schema(&conn, "chrome"), Ok(()));
+}
@@ -0,0 +1,208 @@
+// The history path is only fixed relative to HOME on Linux
+#![cfg(target_os = "linux")]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use rusqlite::{params, Connection};
+use serde_json::Value;
+
+// A throwaway HOME, so the analyzer looks for history (and keeps its state) where the test says
+fn home(name: &str) -> PathBuf {
+    let home = std::env::temp_dir().join(format!("solfhe-exit-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&home);
+    fs::create_dir_all(&home).unwrap();
+    home
+}
+
+fn chrome_history(home: &Path) -> PathBuf {
+    let dir = home.join(".config/google-chrome/Default");
+    fs::create_dir_all(&dir).unwrap();
+    dir.join("History")
+}
+
+fn valid_history(path: &Path) {
+    let conn = Connection::open(path).unwrap();
+    conn.execute("CREATE TABLE urls(id INTEGER PRIMARY KEY, url LONGVARCHAR, last_visit_time INTEGER NOT NULL)", [])
+        .unwrap();
+    conn.execute("INSERT INTO urls (url, last_visit_time) VALUES (?1, ?2)", params!["https://solana.com/docs", 1])
+        .unwrap();
+}
+
+fn analyzer(home: &Path) -> Command {
+    let mut command = Command::cargo_bin("solfhe-analyzer").unwrap();
+    command.env("HOME", home).env_remove("XDG_DATA_HOME").env_remove("XDG_CONFIG_HOME").env_remove("RUST_LOG");
+    command
+}
+
+#[test]
+fn help_documents_the_exit_codes() {
+    let assert = analyzer(&home("help")).arg("--help").assert().code(0);
+    assert!(String::from_utf8_lossy(&assert.get_output().stdout).contains("Exit codes"));
+}
+
+#[test]
+fn unknown_flag_is_bad_usage() {
+    analyzer(&home("usage")).arg("--no-such-flag").assert().code(64);
+    analyzer(&home("usage")).args(["scan", "--since", "last tuesday"]).assert().code(64);
+}
+
+#[test]
+fn missing_history_exits_2() {
+    let home = home("missing");
+    analyzer(&home).args(["export", "json"]).assert().code(2);
+    analyzer(&home).arg("--once").assert().code(2);
+}
+
+#[test]
+fn unreadable_history_exits_3() {
+    let home = home("unreadable");
+    fs::write(chrome_history(&home), "not a sqlite database\n".repeat(200)).unwrap();
+    analyzer(&home).args(["export", "json"]).assert().code(3);
+}
+
+#[test]
+fn doctor_reports_each_check_without_writing_state() {
+    let home = home("doctor");
+    let assert = analyzer(&home).arg("doctor").assert().code(2);
+    let report = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    assert!(report.contains("pass  home directory") && report.contains("fail  history file"), "{}", report);
+    assert!(report.contains("hint: "), "{}", report);
+
+    valid_history(&chrome_history(&home));
+    let assert = analyzer(&home).arg("doctor").assert().code(0);
+    let report = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    assert_eq!(report.lines().filter(|line| line.starts_with("pass  ")).count(), 5, "{}", report);
+    assert!(!home.join(".local/share/solfhe-analyzer").exists());
+}
+
+#[test]
+fn failed_output_write_exits_4() {
+    let home = home("unwritable");
+    valid_history(&chrome_history(&home));
+    let output = home.join("no/such/dir/result.json");
+    analyzer(&home).args(["export", "json", "--output"]).arg(output).assert().code(4);
+}
+
+#[test]
+fn successful_export_reports_no_issues() {
+    let home = home("success");
+    valid_history(&chrome_history(&home));
+    let assert = analyzer(&home).args(["export", "json"]).assert().code(0);
+
+    let envelope: Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(envelope["warnings"], Value::Array(Vec::new()));
+    assert_eq!(envelope["errors"], Value::Array(Vec::new()));
+    assert_eq!(envelope["result"]["word_counts"]["solana"], 1);
+}
+
+#[test]
+fn history_path_replaces_the_browser_location() {
+    let home = home("history-path");
+    let fixture = home.join("fixture.sqlite");
+    analyzer(&home).args(["export", "json", "--history-path"]).arg(&fixture).assert().code(2);
+
+    valid_history(&fixture);
+    let assert = analyzer(&home).args(["export", "json", "--history-path"]).arg(&fixture).assert().code(0);
+    let envelope: Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(envelope["result"]["word_counts"]["solana"], 1);
+}
+
+#[test]
+fn history_appearing_during_retries_is_read() {
+    let home = home("retry");
+    let fixture = home.join("fixture.sqlite");
+    valid_history(&fixture);
+    let history = chrome_history(&home);
+    let appear = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        fs::rename(fixture, history).unwrap();
+    });
+    let assert = analyzer(&home).args(["export", "json", "--history-retry-delay-ms", "300"]).assert().code(0);
+    appear.join().unwrap();
+    let envelope: Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(envelope["result"]["word_counts"]["solana"], 1);
+
+    fs::remove_file(chrome_history(&home)).unwrap();
+    analyzer(&home).args(["export", "json", "--history-retries", "0"]).assert().code(2);
+}
+
+#[test]
+fn visits_table_counts_every_visit() {
+    let home = home("visits");
+    let history = chrome_history(&home);
+    valid_history(&history);
+    let conn = Connection::open(&history).unwrap();
+    conn.execute("CREATE TABLE visits(id INTEGER PRIMARY KEY, url INTEGER NOT NULL, visit_time INTEGER NOT NULL)", []).unwrap();
+    for visit_time in [2, 3, 4] {
+        conn.execute("INSERT INTO visits (url, visit_time) VALUES (1, ?1)", [visit_time]).unwrap();
+    }
+    drop(conn);
+
+    let solana = |flags: &[&str]| {
+        let assert = analyzer(&home).args(["export", "json", "--history-limit", "0"]).args(flags).assert().code(0);
+        let envelope: Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+        envelope["result"]["word_counts"]["solana"].clone()
+    };
+    assert_eq!(solana(&[]), 1);
+    assert_eq!(solana(&["--use-visits-table"]), 3);
+}
+
+#[test]
+fn extractor_patterns_are_checked_at_load_and_counted_verbatim() {
+    let home = home("extractors");
+    let config = home.join("solfhe.toml");
+    fs::write(&config, "[extractors]\nticker = '\\$[A-Z]{2,5}('\n").unwrap();
+    analyzer(&home).args(["export", "json", "--config"]).arg(&config).assert().code(64);
+
+    let history = chrome_history(&home);
+    valid_history(&history);
+    Connection::open(&history)
+        .unwrap()
+        .execute("INSERT INTO urls (url, last_visit_time) VALUES (?1, ?2)", params!["https://x.com/hashtag/%24OP?src=$JUP", 2])
+        .unwrap();
+    fs::write(&config, "[extractors]\nticker = '\\$[A-Z]{2,5}'\n").unwrap();
+    let assert = analyzer(&home).args(["export", "json", "--config"]).arg(&config).assert().code(0);
+
+    let envelope: Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(envelope["result"]["word_counts"]["$OP"], 1);
+    assert_eq!(envelope["result"]["word_counts"]["$JUP"], 1);
+}
+
+#[test]
+fn denylisted_hosts_leave_no_trace_in_the_export() {
+    use sha2::{Digest, Sha256};
+
+    let home = home("denylist");
+    let history = chrome_history(&home);
+    valid_history(&history);
+    let denylisted = "https://secure.chase.com/web/auth/dashboard?account=checking";
+    Connection::open(&history)
+        .unwrap()
+        .execute("INSERT INTO urls (url, last_visit_time) VALUES (?1, ?2)", params![denylisted, 2])
+        .unwrap();
+    let output = home.join("result.json");
+    let assert = analyzer(&home)
+        .env("SOLFHE_SALT", "test-salt")
+        .args(["export", "json", "--detail", "--detail-raw-urls", "-vv", "--output"])
+        .arg(&output)
+        .assert()
+        .code(0);
+
+    let exported = fs::read_to_string(&output).unwrap();
+    let envelope: Value = serde_json::from_str(&exported).unwrap();
+    assert_eq!(envelope["result"]["word_counts"]["solana"], 1);
+    assert_eq!(envelope["result"]["analyzed_urls"].as_array().map(Vec::len), Some(1));
+
+    let mut hasher = Sha256::new();
+    hasher.update(b"test-salt");
+    hasher.update(solfhe_analyzer::analysis::normalize_url(denylisted, &[]).as_bytes());
+    let denylisted_hash = hex::encode(hasher.finalize());
+    let logs = String::from_utf8_lossy(&assert.get_output().stderr).into_owned();
+    for output in [&exported, &logs] {
+        for trace in ["chase", "secure", "dashboard", "checking", denylisted_hash.as_str()] {
+            assert!(!output.contains(trace), "`{}` in {}", trace, output);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use solfhe_analyzer::{extract_keywords_from_url, Config};
+
+fn keywords(url: &str) -> Vec<String> {
+    extract_keywords_from_url(url, &Config::defaults())
+}
+
+#[test]
+fn normal_url_yields_domain_and_path_segments() {
+    assert_eq!(keywords("https://solana.com/docs/core"), ["solana", "docs", "core"]);
+}
+
+#[test]
+fn ignored_words_are_dropped() {
+    assert_eq!(keywords("https://www.ethereum.org/en"), ["ethereum", "en"]);
+}
+
+#[test]
+fn leading_subdomains_are_stripped() {
+    assert_eq!(keywords("https://en.m.wikipedia.org/wiki/Solana"), ["wikipedia", "wiki", "solana"]);
+    assert_eq!(keywords("https://amp.theguardian.com/technology"), ["theguardian", "technology"]);
+    assert_eq!(keywords("https://mobile.twitter.com/solana"), ["twitter", "solana"]);
+    // Only labels in front of the registrable domain are candidates
+    assert_eq!(keywords("https://go.dev/doc"), ["go", "dev", "doc"]);
+    assert_eq!(keywords("https://docs.m.example.com"), ["docs", "m", "example"]);
+}
+
+#[test]
+fn unparseable_url_yields_nothing() {
+    assert_eq!(keywords("http://[::1"), Vec::<String>::new());
+}
+
+#[test]
+fn non_web_schemes_yield_nothing() {
+    for url in ["data:text/html,solana%20staking", "javascript:alert('solana')", "view-source:https://solana.com/docs", "ftp://files.solana.com/docs", "mailto:team@solana.com"] {
+        assert_eq!(keywords(url), Vec::<String>::new(), "{}", url);
+    }
+}
+
+#[test]
+fn local_and_private_hosts_yield_nothing() {
+    for url in ["http://localhost:3000/solana", "http://192.168.1.1/solana", "http://169.254.1.1/solana", "http://[::1]/solana", "http://[fd00::1]/solana", "http://[fe80::1]/solana"] {
+        assert_eq!(keywords(url), Vec::<String>::new(), "{}", url);
+    }
+    assert_eq!(keywords("http://[2001:db8::1]/solana"), ["solana"]);
+}
+
+#[test]
+fn empty_path_yields_only_domain_segments() {
+    assert_eq!(keywords("https://polkadot.network"), ["polkadot", "network"]);
+    assert_eq!(keywords("https://solana.com//docs//"), ["solana", "docs"]);
+}
+
+#[test]
+fn segments_are_lowercased() {
+    assert_eq!(keywords("https://Docs.Solana.com/Cosmos/SDK"), ["docs", "solana", "cosmos", "sdk"]);
+}
+
+#[test]
+fn encoded_path_segments_are_decoded_and_split() {
+    assert_eq!(keywords("https://example.com/topics/solana%20staking"), ["example", "topics", "solana", "staking"]);
+    assert_eq!(keywords("https://example.com/topics/Solana+Staking"), ["example", "topics", "solana", "staking"]);
+    assert_eq!(keywords("https://example.com/page/100%zz%FF"), ["example", "page", "100zzff"]);
+}
+
+#[test]
+fn punctuation_emoji_and_numbers_are_dropped() {
+    assert_eq!(keywords("https://example.com/%E2%80%94/123456/%F0%9F%9A%80solana!/z%C3%BCrich"), ["example", "solana", "zürich"]);
+    assert_eq!(keywords("https://example.com/zk-lokomotive/magic-eden"), ["example", "zk-lokomotive", "magiceden"]);
+}
@@ -0,0 +1,144 @@
+// Needs solana-test-validator on the PATH: cargo test --features test-validator --test submit
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command as Process, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use assert_cmd::Command;
+use rusqlite::{params, Connection};
+use serde_json::Value;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::{write_keypair_file, Keypair, Signature, Signer};
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::UiTransactionEncoding;
+
+// Every test starts its own validator, on its own ports, so they can run in parallel
+const RPC_PORT: u16 = 18899;
+const AIRDROP_RPC_PORT: u16 = 18999;
+
+// Killed when the test ends, passed or not
+struct Validator(Child);
+
+impl Drop for Validator {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn start_validator(dir: &Path, rpc_port: u16) -> (Validator, RpcClient) {
+    // The faucet and the gossip, TPU and other ports are placed after the RPC port
+    let child = Process::new("solana-test-validator")
+        .args(["--reset", "--quiet", "--rpc-port", &rpc_port.to_string(), "--faucet-port", &(rpc_port + 1001).to_string()])
+        .args(["--gossip-port", &(rpc_port + 2).to_string(), "--dynamic-port-range", &format!("{}-{}", rpc_port + 3, rpc_port + 40)])
+        .arg("--ledger")
+        .arg(dir.join("ledger"))
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("solana-test-validator must be on the PATH");
+    let validator = Validator(child);
+    let client = RpcClient::new_with_commitment(format!("http://127.0.0.1:{}", rpc_port), CommitmentConfig::confirmed());
+    let started = Instant::now();
+    while client.get_health().is_err() {
+        assert!(started.elapsed() < Duration::from_secs(60), "the validator did not come up");
+        thread::sleep(Duration::from_millis(500));
+    }
+    (validator, client)
+}
+
+fn history(dir: &Path) -> PathBuf {
+    let path = dir.join("History");
+    let conn = Connection::open(&path).unwrap();
+    conn.execute("CREATE TABLE urls(id INTEGER PRIMARY KEY, url LONGVARCHAR, last_visit_time INTEGER NOT NULL)", [])
+        .unwrap();
+    conn.execute("INSERT INTO urls (url, last_visit_time) VALUES (?1, ?2)", params!["https://solana.com/docs", 1])
+        .unwrap();
+    path
+}
+
+#[test]
+fn submitted_digest_lands_in_a_memo() {
+    let dir = std::env::temp_dir().join(format!("solfhe-submit-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let (validator, client) = start_validator(&dir, RPC_PORT);
+
+    let payer = Keypair::new();
+    let keypair_path = dir.join("payer.json");
+    write_keypair_file(&payer, &keypair_path).unwrap();
+    let airdrop = client.request_airdrop(&payer.pubkey(), 1_000_000_000).unwrap();
+    while !client.confirm_transaction(&airdrop).unwrap() {
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    let assert = Command::cargo_bin("solfhe-analyzer").unwrap()
+        .env("HOME", &dir)
+        .env_remove("XDG_DATA_HOME")
+        .env_remove("RUST_LOG")
+        .args(["--once", "--submit", "solana", "--rpc-url", &format!("http://127.0.0.1:{}", RPC_PORT), "--history-path"])
+        .arg(history(&dir))
+        .arg("--keypair")
+        .arg(&keypair_path)
+        .assert()
+        .code(0);
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    let envelope: Value = serde_json::from_str(stdout.lines().last().expect("one result")).unwrap();
+    assert_eq!(envelope["errors"], Value::Array(Vec::new()));
+
+    let signature: Signature = envelope["memo_signature"].as_str().expect("memo_signature").parse().unwrap();
+    let digest = hex::decode(envelope["compression_stats"]["sha256"].as_str().unwrap()).unwrap();
+    let memo = format!("solfhe:v{}:{}", envelope["schema_version"], bs58::encode(digest).into_string());
+    let transaction = client.get_transaction(&signature, UiTransactionEncoding::Json).unwrap();
+    let OptionSerializer::Some(logs) = transaction.transaction.meta.unwrap().log_messages else {
+        panic!("the transaction has no logs");
+    };
+    assert!(logs.iter().any(|log| log.contains(&memo)), "{} not in {:?}", memo, logs);
+
+    let verified = Command::cargo_bin("solfhe-analyzer").unwrap()
+        .env("HOME", &dir)
+        .env_remove("XDG_DATA_HOME")
+        .env_remove("RUST_LOG")
+        .args(["verify-onchain", "--batch", "1", "--rpc-url", &format!("http://127.0.0.1:{}", RPC_PORT)])
+        .assert()
+        .code(0);
+    let report = String::from_utf8_lossy(&verified.get_output().stdout).into_owned();
+    assert!(report.starts_with("1\tanchored\n") && report.contains("1 batches: 1 anchored"), "{}", report);
+    drop(validator);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unfunded_localnet_wallet_is_topped_up_by_airdrop() {
+    let dir = std::env::temp_dir().join(format!("solfhe-airdrop-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let (validator, client) = start_validator(&dir, AIRDROP_RPC_PORT);
+
+    let payer = Keypair::new();
+    let keypair_path = dir.join("payer.json");
+    write_keypair_file(&payer, &keypair_path).unwrap();
+    let rpc_url = format!("http://127.0.0.1:{}", AIRDROP_RPC_PORT);
+    let analyzer = || {
+        let mut command = Command::cargo_bin("solfhe-analyzer").unwrap();
+        command.env("HOME", &dir).env_remove("XDG_DATA_HOME").env_remove("RUST_LOG");
+        command.args(["--cluster", "localnet", "--rpc-url", &rpc_url]).arg("--keypair").arg(&keypair_path);
+        command
+    };
+
+    let status = analyzer().args(["wallet", "status"]).assert().code(0);
+    let status = String::from_utf8_lossy(&status.get_output().stdout).into_owned();
+    assert!(status.contains(&payer.pubkey().to_string()), "{}", status);
+    assert!(status.contains("balance: 0 SOL") && status.contains("cluster: localnet"), "{}", status);
+
+    let assert = analyzer().args(["--once", "--submit", "solana", "--history-path"]).arg(history(&dir)).assert().code(0);
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    let envelope: Value = serde_json::from_str(stdout.lines().last().expect("one result")).unwrap();
+    assert_eq!(envelope["errors"], Value::Array(Vec::new()));
+    assert_eq!(envelope["cluster"], "localnet");
+    assert!(envelope["memo_signature"].is_string());
+    assert!(client.get_balance(&payer.pubkey()).unwrap() > 0);
+    drop(validator);
+    fs::remove_dir_all(&dir).unwrap();
+}